@@ -2,40 +2,108 @@
 //!
 //! Shell processes to be run
 //!
+//! Commands are configurable templates, split on whitespace. Arguments
+//! containing `{path}`, `{dir}`, `{start}` (seconds) or `{subs}` get the
+//! value substituted, or are dropped if there is none.
 
+use std::path::Path;
 use std::process::{Command, Stdio};
+use std::thread;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 
+use super::timestamp::Timestamp;
 use super::Entry;
+use crate::DATABASE;
 
-pub fn open_vlc(entry: Entry) -> Result<()> {
-    let _ = Command::new("vlc")
-        .arg(entry.out_path()?)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+/// Play command templates by player name
+pub const PLAYERS: [(&str, &str); 2] = [
+    ("vlc", "vlc --start-time={start} --sub-file={subs} {path}"),
+    ("mpv", "mpv --start={start} --sub-file={subs} {path}"),
+];
 
-    Ok(())
+/// Plays the clip from `start` with its subtitles.
+pub fn play(entry: Entry, start: Option<Timestamp>) -> Result<()> {
+    let millis = start.unwrap_or_default().to_millis();
+    let subs = entry.srt_path()?;
+    let vars = [
+        (
+            "{start}",
+            Some(format!("{}.{:03}", millis / 1000, millis % 1000)),
+        ),
+        ("{subs}", Some(subs).filter(|s| Path::new(s).exists())),
+    ];
+    run(
+        DATABASE.get_config()?.play_command(),
+        &entry.out_path()?,
+        &vars,
+    )
 }
 
 pub fn open_file(path: &str) -> Result<()> {
-    let _ = Command::new("vlc")
-        .arg(path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+    run(DATABASE.get_config()?.open_command(), path, &[])
+}
 
-    Ok(())
+pub fn open_folder(entry: Entry) -> Result<()> {
+    run(
+        DATABASE.get_config()?.folder_command(),
+        &entry.out_path()?,
+        &[],
+    )
 }
 
-pub fn open_nautilus(entry: Entry) -> Result<()> {
-    let _ = Command::new("nautilus")
-        .arg("--select")
-        .arg(entry.out_path()?)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()?;
+fn run(template: &str, path: &str, vars: &[(&str, Option<String>)]) -> Result<()> {
+    let args = expand(template, path, vars);
+    let (program, args) = args.split_first().ok_or(anyhow!("empty command"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow!("unable to start {}: {}", program, e))?;
 
+    // Reaped once closed, the UI doesn't wait for it
+    let _ = thread::spawn(move || child.wait());
     Ok(())
 }
+
+// Splits the template into arguments with the placeholders substituted
+fn expand(template: &str, path: &str, vars: &[(&str, Option<String>)]) -> Vec<String> {
+    let dir = Path::new(path)
+        .parent()
+        .map(|d| d.to_string_lossy().to_string());
+    let path = Some(path.to_string());
+    let vars = [("{path}", &path), ("{dir}", &dir)]
+        .into_iter()
+        .chain(vars.iter().map(|(k, v)| (*k, v)));
+
+    let mut args: Vec<String> = template.split_whitespace().map(String::from).collect();
+    for (key, value) in vars {
+        args = args
+            .into_iter()
+            .filter_map(|a| match value {
+                _ if !a.contains(key) => Some(a),
+                Some(v) => Some(a.replace(key, v)),
+                None => None,
+            })
+            .collect();
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand() {
+        let vars = [("{start}", Some(String::from("1.500"))), ("{subs}", None)];
+        let args = expand(PLAYERS[1].1, "/data/0/my clip.mp4", &vars);
+        assert_eq!(args, ["mpv", "--start=1.500", "/data/0/my clip.mp4"]);
+        assert_eq!(
+            expand("xdg-open {dir}", "/data/0/1.mp4", &[]),
+            ["xdg-open", "/data/0"]
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! # Check
+//!
+//! Cross references the stored entries with the search index and the files
+//! under `ROOT`, every issue found comes with a fix. Orphaned files are left
+//! out of fixing everything.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use polodb_core::bson::doc;
+
+use super::job::{Job, JobState};
+use super::{trash, Entry};
+use crate::{DATABASE, ROOT};
+
+// Folders with one file per entry, named by its id
+const FOLDERS: [&str; 3] = ["data", "subs", "meta"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Issue {
+    /// Entry without its media file
+    MissingMedia(i64),
+    /// File in one of the entry folders without an entry
+    Orphan(PathBuf),
+    /// Entry indexed more than once
+    DuplicateIndex(i64, usize),
+    /// Entry missing from the index
+    MissingIndex(i64),
+    /// Index document without an entry
+    StaleIndex(i64),
+    /// Temp folder of no unfinished job
+    StaleTemp(PathBuf),
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingMedia(id) => write!(f, "entry {} has no media file", id),
+            Self::Orphan(path) => write!(f, "{} belongs to no entry", path.display()),
+            Self::DuplicateIndex(id, n) => write!(f, "entry {} is indexed {} times", id, n),
+            Self::MissingIndex(id) => write!(f, "entry {} is not indexed", id),
+            Self::StaleIndex(id) => write!(f, "index has entry {} which doesn't exist", id),
+            Self::StaleTemp(path) => write!(f, "{} belongs to no unfinished job", path.display()),
+        }
+    }
+}
+
+impl Issue {
+    /// Describes what `fix` does.
+    pub fn remedy(&self) -> &'static str {
+        match self {
+            Self::MissingMedia(_) => "move the entry to the trash",
+            Self::Orphan(_) => "move the file to trash/orphans",
+            Self::DuplicateIndex(..) | Self::MissingIndex(_) => "reindex the entry",
+            Self::StaleIndex(_) => "remove it from the index",
+            Self::StaleTemp(_) => "delete the folder",
+        }
+    }
+
+    /// Orphans might be files the check doesn't know about, so they are only
+    /// moved when asked for explicitly.
+    pub fn is_orphan(&self) -> bool {
+        matches!(self, Self::Orphan(_))
+    }
+
+    pub fn fix(&self) -> Result<()> {
+        match self {
+            Self::MissingMedia(id) => trash::delete(&entry(*id)?),
+            Self::Orphan(path) => {
+                let rel = path.strip_prefix(ROOT.as_str())?;
+                let to = Path::new(ROOT.as_str()).join("trash/orphans").join(rel);
+                if let Some(dir) = to.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::rename(path, to)?;
+                Ok(())
+            }
+            Self::DuplicateIndex(id, _) | Self::MissingIndex(id) => {
+                DATABASE.index_replace(*id, &entry(*id)?)
+            }
+            Self::StaleIndex(id) => DATABASE.index_remove(*id),
+            Self::StaleTemp(path) => Ok(fs::remove_dir_all(path)?),
+        }
+    }
+}
+
+/// Finds all issues, ordered by kind.
+pub fn check() -> Result<Vec<Issue>> {
+    let (entries, broken) = Entry::all()?;
+    // Files of undecodable entries aren't orphans either
+    let ids: HashSet<i64> = entries.iter().map(|e| e.id()).chain(broken).collect();
+    // Files of these are still being written
+    let active: HashSet<i64> = DATABASE
+        .get_all::<Job>()?
+        .filter_map(|j| j.ok())
+        .filter(|j| j.state() != JobState::Done)
+        .filter_map(|j| j.id().map(|id| id.get()))
+        .collect();
+
+    let mut issues = Vec::new();
+    for entry in &entries {
+        if !Path::new(&entry.out_path()?).exists() {
+            issues.push(Issue::MissingMedia(entry.id()));
+        }
+    }
+
+    for folder in FOLDERS {
+        for path in files(&format!("{}/{}/", ROOT.as_str(), folder))? {
+            let id = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(|s| s.parse::<i64>().ok());
+            match id {
+                Some(id) if ids.contains(&id) || active.contains(&id) => {}
+                _ => issues.push(Issue::Orphan(path)),
+            }
+        }
+    }
+
+    let mut indexed: HashMap<i64, usize> = HashMap::new();
+    for id in DATABASE.indexed_ids()? {
+        *indexed.entry(id).or_default() += 1;
+    }
+    for entry in &entries {
+        match indexed.get(&entry.id()) {
+            None => issues.push(Issue::MissingIndex(entry.id())),
+            Some(n) if *n > 1 => issues.push(Issue::DuplicateIndex(entry.id(), *n)),
+            Some(_) => {}
+        }
+    }
+    let mut stale: Vec<i64> = indexed.into_keys().filter(|id| !ids.contains(id)).collect();
+    stale.sort();
+    issues.extend(stale.into_iter().map(Issue::StaleIndex));
+
+    for dir in fs::read_dir(format!("{}/temp/", ROOT.as_str()))? {
+        let path = dir?.path();
+        let id = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.parse::<i64>().ok());
+        match id {
+            Some(id) if active.contains(&id) => {}
+            _ => issues.push(Issue::StaleTemp(path)),
+        }
+    }
+
+    Ok(issues)
+}
+
+fn entry(id: i64) -> Result<Entry> {
+    DATABASE
+        .get_one(doc! {"_id": id})
+        .map_err(|_| anyhow!("entry {} doesn't exist", id))
+}
+
+// Files in the bucket folders below `dir`, sorted
+fn files(dir: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for bucket in fs::read_dir(dir)? {
+        let bucket = bucket?.path();
+        if !bucket.is_dir() {
+            files.push(bucket);
+            continue;
+        }
+        for file in fs::read_dir(bucket)? {
+            files.push(file?.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,106 @@
+//! # Cut
+//!
+//! Plans the ffmpeg invocations of the cut pass.
+
+use serde::{Deserialize, Serialize};
+
+use super::metadata::{self, Metadata};
+use super::profile::Profile;
+use super::timestamp::Timestamp;
+
+const X264: [&str; 6] = ["-c:v", "libx264", "-crf", "18", "-preset", "fast"];
+const X265: [&str; 6] = ["-c:v", "libx265", "-crf", "18", "-preset", "fast"];
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CutMode {
+    /// Stream copy, start snapped to the previous keyframe
+    #[default]
+    Keyframe,
+    /// Stream copy starting on a keyframe, or an audio only cut
+    Exact,
+    /// Head up to the next keyframe re-encoded, the rest copied
+    Head,
+    /// Whole clip re-encoded
+    Encode,
+}
+
+/// Single ffmpeg invocation of the cut pass
+#[derive(Clone, Debug)]
+pub struct Part {
+    pub start: Timestamp,
+    pub duration: Timestamp,
+    // ffmpeg output arguments
+    pub args: Vec<String>,
+}
+
+/// Splits the range into parts, re-encoding as little as possible for an
+/// accurate start.
+pub fn plan(
+    file: &str,
+    start: Timestamp,
+    end: Timestamp,
+    profile: &Profile,
+    meta: Option<&Metadata>,
+    accurate: bool,
+) -> (CutMode, Vec<Part>) {
+    let audio = meta.map(|m| m.audio_streams()).unwrap_or(1);
+    let whole = |args: Vec<String>| Part {
+        start,
+        duration: end - start,
+        args,
+    };
+
+    if !profile.has_video() {
+        return (CutMode::Exact, vec![whole(profile.args(audio))]);
+    }
+    if !profile.stream_copy() {
+        // Transcoding with an input seek is already frame accurate
+        return (CutMode::Encode, vec![whole(profile.args(audio))]);
+    }
+    if !accurate {
+        return (CutMode::Keyframe, vec![whole(profile.args(audio))]);
+    }
+
+    let codec = meta.and_then(|m| m.video()).map(|v| v.codec().to_string());
+    let encoder: &[&str] = match codec.as_deref() {
+        Some("h264") => &X264,
+        Some("hevc") => &X265,
+        _ => {
+            // Can't be concatenated with copied parts
+            return (
+                CutMode::Encode,
+                vec![whole(profile.encode_args(audio, &X264))],
+            );
+        }
+    };
+
+    let keyframes = metadata::keyframes(file, start, end).unwrap_or_default();
+    if keyframes
+        .iter()
+        .any(|k| (k.to_millis() - start.to_millis()).abs() <= 1)
+    {
+        return (CutMode::Exact, vec![whole(profile.args(audio))]);
+    }
+
+    match keyframes.iter().find(|k| **k > start && **k < end) {
+        Some(next) => {
+            let head = Part {
+                start,
+                duration: *next - start,
+                args: profile.encode_args(audio, encoder),
+            };
+            // Seeking slightly past the keyframe makes ffmpeg start on it
+            let from = Timestamp::from_millis(next.to_millis() + 1);
+            let tail = Part {
+                start: from,
+                duration: end - *next,
+                args: profile.args(audio),
+            };
+            (CutMode::Head, vec![head, tail])
+        }
+        None => (
+            CutMode::Encode,
+            vec![whole(profile.encode_args(audio, encoder))],
+        ),
+    }
+}
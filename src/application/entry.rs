@@ -2,14 +2,18 @@
 //!
 //! In PoloDB store
 
-use std::fs::metadata;
+use std::collections::BTreeSet;
+use std::fs::{self, metadata};
 
-use super::{id::Id, indexed::Indexed, tag::Tag, timestamp::Timestamp};
+use super::{
+    cut::CutMode, id::Id, indexed::Indexed, language::Language, segment::Segment, tag::Tag,
+    timestamp::Timestamp,
+};
 use crate::{store::Entity, DATABASE};
 use anyhow::{anyhow, Result};
 use chrono::NaiveDate;
 use lipsum::{lipsum_with_rng, lipsum_words_with_rng};
-use polodb_core::bson::doc;
+use polodb_core::bson::{self, doc, from_document, to_bson};
 use rand::thread_rng;
 use serde::{Deserialize, Serialize};
 use tantivy::{schema::Schema, DateTime, Document};
@@ -24,6 +28,15 @@ pub struct Entry {
     date: NaiveDate,
     tags: Vec<Tag>,
     file: FileInfo,
+    // How the clip was cut from the source
+    #[serde(default)]
+    cut: CutMode,
+    // Spoken language, as given or detected by the transcriber
+    #[serde(default)]
+    language: Language,
+    // Transcript with timestamps, from the SRT
+    #[serde(default)]
+    segments: Vec<Segment>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -35,6 +48,7 @@ pub struct FileInfo {
 }
 
 impl Entry {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: Id,
         file: &str,
@@ -44,6 +58,9 @@ impl Entry {
         date: NaiveDate,
         tags: Vec<Tag>,
         duration: Timestamp,
+        cut: CutMode,
+        language: Language,
+        segments: Vec<Segment>,
     ) -> Result<Self> {
         Ok(Self {
             id,
@@ -53,24 +70,132 @@ impl Entry {
             date,
             tags,
             file: FileInfo::new(file, duration)?,
+            cut,
+            language,
+            segments,
         })
     }
 
     pub fn search_str(&self) -> String {
         format!(
-            "{} - {} - {} - {} - {:?}",
+            "{} - {} - {} - {} - {:?} - {}",
             self.id.get(),
             self.date.format("%d-%m-%Y").to_string(),
             self.title,
             self.description,
-            self.tags
+            self.tags,
+            self.language
         )
     }
 
+    /// Every stored entry ordered by id, and the ids of the documents which
+    /// failed to decode.
+    pub fn all() -> Result<(Vec<Entry>, Vec<i64>)> {
+        let mut raws = DATABASE
+            .get_all_raw::<Entry>()?
+            .collect::<Result<Vec<bson::Document>, _>>()?;
+        // PoloDB scans miss documents still in the write-ahead log, which are
+        // found by id
+        let scanned: BTreeSet<i64> = raws.iter().filter_map(|r| r.get_i64("_id").ok()).collect();
+        let last = DATABASE.get_config()?.get_counter();
+        for i in (0..=last).filter(|i| !scanned.contains(i)) {
+            raws.extend(DATABASE.get_one_raw::<Entry>(doc! {"_id": i})?);
+        }
+
+        let mut entries = Vec::new();
+        let mut broken = Vec::new();
+        for raw in raws {
+            let id = raw.get_i64("_id").unwrap_or(-1);
+            match from_document::<Entry>(raw) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => broken.push(id),
+            }
+        }
+        entries.sort_by_key(|e| e.id());
+        broken.sort();
+        Ok((entries, broken))
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id.get()
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn transcript(&self) -> &str {
+        &self.transcript
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    /// Changes the editable fields, same input format as for jobs.
+    pub fn edit(
+        &mut self,
+        title: &[String],
+        description: &[String],
+        tags: &[String],
+        date: &[String],
+    ) -> Result<()> {
+        let title = title.first().map(|t| t.trim()).unwrap_or_default();
+        if title.is_empty() {
+            return Err(anyhow!("title is empty"));
+        }
+        let date = match date.first() {
+            Some(d) => NaiveDate::parse_from_str(d.trim(), "%d-%m-%Y")?,
+            None => return Err(anyhow!("invalid timestamp")),
+        };
+        let parsed = Tag::parse(tags)?;
+
+        self.title = title.to_string();
+        self.description = description.join("\n");
+        self.tags = parsed;
+        self.date = date;
+
+        let changes = doc! {
+            "title": &self.title,
+            "description": &self.description,
+            "tags": to_bson(&self.tags)?,
+            "date": to_bson(&self.date)?,
+        };
+        DATABASE.update_indexed(self.id.get(), changes, self)?;
+        self.write_meta()
+    }
+
+    /// Sidecar JSON next to the media, kept in sync with the store.
+    pub fn write_meta(&self) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(self.meta_path()?, json)?;
+        Ok(())
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
     pub fn out_path(&self) -> Result<String> {
         self.id.data_path()
     }
 
+    pub fn srt_path(&self) -> Result<String> {
+        self.id.srt_out()
+    }
+
+    pub fn meta_path(&self) -> Result<String> {
+        self.id.meta_path()
+    }
+
     pub fn get_meta(&self) -> FileInfo {
         self.file.clone()
     }
@@ -98,13 +223,16 @@ impl FileInfo {
 impl Default for Entry {
     fn default() -> Self {
         Self {
-            id: Id::default(),
+            id: Id::next().expect("Unable to allocate an id"),
             title: lipsum_words_with_rng(thread_rng(), 6),
             description: lipsum_words_with_rng(thread_rng(), 18),
             transcript: lipsum_with_rng(thread_rng(), 64),
             date: Default::default(),
             tags: Default::default(),
             file: Default::default(),
+            cut: Default::default(),
+            language: Default::default(),
+            segments: Default::default(),
         }
     }
 }
@@ -145,6 +273,7 @@ impl Entity for Entry {
         let time = schema.get_field("timestamp")?;
         let size = schema.get_field("size")?;
         let duration = schema.get_field("duration")?;
+        let language = schema.get_field("language")?;
 
         let mut doc = Document::default();
 
@@ -159,6 +288,10 @@ impl Entity for Entry {
         let timestamp = DateTime::from_timestamp_secs(to_time.timestamp());
         doc.add_date(time, timestamp);
 
+        if let Some(code) = self.language.code() {
+            doc.add_text(language, code);
+        }
+
         for tag in self.tags.iter() {
             doc.add_text(tags, tag.to_string());
         }
@@ -0,0 +1,111 @@
+//! # Export
+//!
+//! Copies of entries with their subtitles, written to the export folder.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Result};
+
+use super::{Entry, Metadata};
+use crate::ROOT;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subtitles {
+    /// Rendered into the video, re-encodes it
+    Burned,
+    /// Separate mov_text track, streams are copied
+    Soft,
+}
+
+/// Writes the export of the entry and returns its path.
+pub fn export(entry: &Entry, subtitles: Subtitles) -> Result<String> {
+    let media = entry.out_path()?;
+    let srt = entry.srt_path()?;
+    if !Path::new(&srt).exists() {
+        return Err(anyhow!("no subtitles for entry {}", entry.id()));
+    }
+
+    let out = match subtitles {
+        Subtitles::Burned => format!("{}/export/{}-burned.mp4", ROOT.as_str(), entry.id()),
+        Subtitles::Soft => format!("{}/export/{}-soft.mp4", ROOT.as_str(), entry.id()),
+    };
+
+    let mut cmd = Command::new("ffmpeg");
+    cmd.arg("-y")
+        .arg("-hide_banner")
+        .arg("-v")
+        .arg("error")
+        .arg("-i")
+        .arg(&media);
+    match subtitles {
+        Subtitles::Burned => {
+            let meta = Metadata::new(media.clone())?;
+            if meta.video().is_none() {
+                return Err(anyhow!("entry {} has no video stream", entry.id()));
+            }
+            cmd.arg("-vf")
+                .arg(format!("subtitles=filename={}", escape(&srt)))
+                .arg("-c:a")
+                .arg("copy");
+        }
+        Subtitles::Soft => {
+            cmd.arg("-i")
+                .arg(&srt)
+                .arg("-map")
+                .arg("0")
+                .arg("-map")
+                .arg("1")
+                .arg("-c")
+                .arg("copy")
+                .arg("-c:s")
+                .arg("mov_text");
+        }
+    }
+
+    let output = cmd
+        .arg(&out)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "export failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(out)
+}
+
+// Escapes the option value, then the filter graph
+fn escape(path: &str) -> String {
+    let mut option = String::new();
+    for c in path.chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+    let mut graph = String::new();
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            graph.push('\\');
+        }
+        graph.push(c);
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("/ccp/subs/0/1.srt"), "/ccp/subs/0/1.srt");
+        assert_eq!(escape("C:/a,b"), "C\\\\:/a\\,b");
+    }
+}
@@ -0,0 +1,72 @@
+//! # Hit
+//!
+//! Search result together with the transcript segments matching the query.
+
+use anyhow::Result;
+
+use super::segment::Segment;
+use super::Entry;
+use crate::store::{Snippet, Sort};
+use crate::DATABASE;
+
+/// Hits fetched at once, the next page is loaded when needed.
+pub const PAGE: usize = 100;
+
+// Length of the snippets, about one line
+const SNIPPET: usize = 120;
+
+#[derive(Clone, Debug)]
+pub struct Hit {
+    pub entry: Entry,
+    pub segments: Vec<Segment>,
+    pub description: Snippet,
+    pub transcript: Snippet,
+}
+
+impl Hit {
+    /// One page of hits, starting after the first `offset`. Also returns the
+    /// number of hits in the index, the next page starts after them.
+    pub fn search(
+        query: &str,
+        sort: Sort,
+        reverse: bool,
+        offset: usize,
+    ) -> Result<(Vec<Self>, usize)> {
+        let mut matcher = DATABASE.matcher(query, "transcript")?;
+        let (found, count): (Vec<Entry>, usize) =
+            DATABASE.search(query, sort, reverse, offset, PAGE)?;
+        let description = DATABASE.highlighter(query, "description", SNIPPET)?;
+        let transcript = DATABASE.highlighter(query, "transcript", SNIPPET)?;
+        let hits = found
+            .into_iter()
+            .map(|entry| {
+                let segments = entry
+                    .segments()
+                    .iter()
+                    .filter(|s| s.matches(&mut matcher))
+                    .cloned()
+                    .collect();
+                Self {
+                    description: description.snippet(entry.description()),
+                    transcript: transcript.snippet(entry.transcript()),
+                    entry,
+                    segments,
+                }
+            })
+            .collect();
+        Ok((hits, count))
+    }
+
+    pub fn search_str(&self) -> String {
+        if self.segments.is_empty() {
+            return self.entry.search_str();
+        }
+        let times = self
+            .segments
+            .iter()
+            .map(|s| s.start.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        format!("{} @ {}", self.entry.search_str(), times)
+    }
+}
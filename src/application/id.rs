@@ -1,6 +1,7 @@
 //! # Id
 
 use std::fs;
+use std::path::Path;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -10,17 +11,18 @@ use crate::{DATABASE, ROOT};
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Id(i64);
 
-impl Default for Id {
-    fn default() -> Self {
-        Self(
-            DATABASE
-                .get_config_incr()
-                .expect("Unable to parse configuration!"),
-        )
+impl Id {
+    /// Allocates the next unused id.
+    pub fn next() -> Result<Self> {
+        // Ids of deleted entries aren't handed out again, even if the counter was reset
+        loop {
+            let id = Self(DATABASE.get_config_incr()?);
+            if !id.is_used() {
+                return Ok(id);
+            }
+        }
     }
-}
 
-impl Id {
     pub fn get(&self) -> i64 {
         self.0
     }
@@ -37,6 +39,16 @@ impl Id {
         Ok(format!("{}pass_1.mp4", self.temp_dir()?))
     }
 
+    /// Intermediate file of the cut pass
+    pub fn part_path(&self, part: usize) -> Result<String> {
+        Ok(format!("{}part_{}.mp4", self.temp_dir()?, part))
+    }
+
+    /// ffmpeg concat list of all parts
+    pub fn concat_path(&self) -> Result<String> {
+        Ok(format!("{}parts.txt", self.temp_dir()?))
+    }
+
     pub fn text_path(&self) -> Result<String> {
         Ok(format!("{}pass_1.txt", self.temp_dir()?))
     }
@@ -67,6 +79,18 @@ impl Id {
         Ok(path + &last)
     }
 
+    // Any files left under this id
+    fn is_used(&self) -> bool {
+        let (root, s, id) = (ROOT.as_str(), self.get_s(), self.0);
+        [
+            format!("{}/data/{}/{}.mp4", root, s, id),
+            format!("{}/meta/{}/{}.json", root, s, id),
+            format!("{}/trash/{}/", root, id),
+        ]
+        .iter()
+        .any(|p| Path::new(p).exists())
+    }
+
     fn get_s(&self) -> i64 {
         self.0 / 100
     }
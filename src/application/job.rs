@@ -1,37 +1,123 @@
 //! # Job
 
+use std::fmt::Display;
+
 use anyhow::{anyhow, Result};
-use chrono::{Local, NaiveDate};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use polodb_core::bson::{doc, to_bson, Bson, Document};
 use uuid::Uuid;
 
 use crate::{application::tag::Tag, store::Entity, DATABASE, ROOT};
 use serde::{Deserialize, Serialize};
 
-use super::{id::Id, timestamp::Timestamp, Entry};
+use super::cut::CutMode;
+use super::language::Language;
+use super::profile::{self, Profile};
+use super::segment::Segment;
+use super::timestamp::{Span, Timestamp};
+use super::{id::Id, Entry, Metadata};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Job {
     pub uid: String,
     file: String,
-    start: Timestamp,
+    // Ranges of the file, cut and joined in this order
+    #[serde(default)]
+    spans: Vec<Span>,
+    // Single range of jobs stored before multiple ranges were supported
+    #[serde(default, skip_serializing)]
+    start: Option<Timestamp>,
+    #[serde(default, skip_serializing)]
+    end: Option<Timestamp>,
     date: NaiveDate,
-    end: Timestamp,
     title: String,
     language: Language,
     description: String,
     tags: Vec<Tag>,
+    // Name of the encoding profile used by the cut pass
+    #[serde(default = "default_profile")]
+    profile: String,
+    // Re-encode around the start instead of snapping to a keyframe
+    #[serde(default)]
+    accurate: bool,
+    // Mode used by the cut pass, kept for resumed jobs
+    #[serde(default)]
+    cut: CutMode,
     done: bool,
+    #[serde(default)]
+    state: JobState,
+    // Error message or stderr of the last failed attempt
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    attempts: u32,
+    // Id assigned by the first attempt, reused when resuming
+    #[serde(default)]
+    id: Option<Id>,
+    // Checkpoint, number of passes finished in the temp folder of the id
+    #[serde(default)]
+    passes: u8,
+    #[serde(default = "now")]
+    created: NaiveDateTime,
+    #[serde(default = "now")]
+    updated: NaiveDateTime,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum JobState {
+    #[default]
+    Queued,
+    Cutting,
+    Transcribing,
+    Finalizing,
+    Failed,
+    Cancelled,
+    Done,
+}
+
+impl JobState {
+    /// Failed and cancelled jobs have to be retried explicitly, active states are left over
+    /// from an interrupted run and get picked up again.
+    pub fn is_runnable(&self) -> bool {
+        !matches!(self, Self::Failed | Self::Cancelled | Self::Done)
+    }
+}
+
+impl Display for JobState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Queued => "Queued",
+            Self::Cutting => "Cutting",
+            Self::Transcribing => "Transcribing",
+            Self::Finalizing => "Finalizing",
+            Self::Failed => "Failed",
+            Self::Cancelled => "Cancelled",
+            Self::Done => "Done",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+fn default_profile() -> String {
+    profile::DEFAULT.to_string()
+}
+
+fn now() -> NaiveDateTime {
+    Local::now().naive_local()
 }
 
 impl ToString for Job {
     fn to_string(&self) -> String {
         format!(
-            "{} - {} : {} ({}-{})",
+            "{} - {} : {} ({})",
             self.date.to_string(),
             self.title,
             self.description,
-            self.start.to_string(),
-            self.end.to_string()
+            self.spans()
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
         )
     }
 }
@@ -41,56 +127,32 @@ impl Default for Job {
         Self {
             uid: Uuid::new_v4().to_string(),
             file: format!("{}/ingest/test.mp4", ROOT.as_str()),
-            start: Timestamp::from_str("00:01:00").unwrap(),
-            end: Timestamp::from_str("00:01:45").unwrap(),
+            spans: vec![Span {
+                start: Timestamp::from_str("00:01:00").unwrap(),
+                end: Timestamp::from_str("00:01:45").unwrap(),
+            }],
+            start: None,
+            end: None,
             date: Local::now().date_naive(),
             title: format!("Some Test Clip"),
             description: format!("some more lipsum stuff"),
-            language: Language::DE,
+            language: Language::Iso("de"),
             tags: vec![Tag::new("cs2").unwrap()],
+            profile: default_profile(),
+            accurate: false,
+            cut: CutMode::default(),
             done: false,
+            state: JobState::Queued,
+            error: None,
+            attempts: 0,
+            id: None,
+            passes: 0,
+            created: now(),
+            updated: now(),
         }
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum Language {
-    EN,
-    DE,
-}
-
-impl Language {
-    pub fn from_str(s: &str) -> Language {
-        let s = s.trim();
-        match s {
-            "en" => Self::EN,
-            _ => Self::DE,
-        }
-    }
-
-    pub fn from_input(lines: &[String]) -> Language {
-        for l in lines {
-            return Self::from_str(l);
-        }
-        Self::default()
-    }
-}
-
-impl ToString for Language {
-    fn to_string(&self) -> String {
-        match self {
-            Language::EN => String::from("German"),
-            Language::DE => String::from("Language"),
-        }
-    }
-}
-
-impl Default for Language {
-    fn default() -> Self {
-        Self::DE
-    }
-}
-
 impl Job {
     pub fn new(
         file: String,
@@ -101,6 +163,8 @@ impl Job {
         lang_vec: &[String],
         date_input: &[String],
         tags: &[String],
+        profile: Option<Profile>,
+        accurate: bool,
     ) -> Result<Self> {
         let file = {
             if file == "" {
@@ -109,8 +173,7 @@ impl Job {
             file
         };
 
-        let start = Timestamp::from_input(start_ts)?;
-        let end = Timestamp::from_input(end_ts)?;
+        let spans = Span::from_input(start_ts, end_ts)?;
         let title = {
             if let Some(l) = title_vec.first() {
                 l.clone()
@@ -125,16 +188,9 @@ impl Job {
                 String::from("")
             }
         };
-        let language = Language::from_input(lang_vec);
-        let mut parsed_tags = Vec::new();
-        for tgs in tags {
-            // First line is all tags
-            let tag = tgs.split(" ").collect::<Vec<&str>>();
-            for t in tag {
-                let created_tag = Tag::new(t)?;
-                parsed_tags.push(created_tag);
-            }
-        }
+        let language = Language::from_input(lang_vec)?;
+        // First line is all tags
+        let parsed_tags = Tag::parse(tags)?;
         let date = {
             if let Some(d) = date_input.first() {
                 NaiveDate::parse_from_str(&d, "%d-%m-%Y")?
@@ -147,37 +203,161 @@ impl Job {
             return Err(anyhow!("invalid job"));
         }
 
+        let profile = match (profile, Metadata::new(file.clone())) {
+            (Some(p), _) => p.name().to_string(),
+            (None, Ok(meta)) => Profile::select(&meta).to_string(),
+            (None, Err(_)) => default_profile(),
+        };
+
         let job = Self {
             uid: Uuid::new_v4().to_string(),
             file,
-            start,
+            spans,
+            start: None,
+            end: None,
             date,
-            end,
             title,
             description,
             language,
             tags: parsed_tags,
+            profile,
+            accurate,
+            cut: CutMode::default(),
             done: false,
+            state: JobState::Queued,
+            error: None,
+            attempts: 0,
+            id: None,
+            passes: 0,
+            created: now(),
+            updated: now(),
         };
 
         let _ = DATABASE.insert(job.clone())?;
         Ok(job)
     }
 
-    pub fn start(&self) -> Timestamp {
-        self.start.clone()
+    pub fn spans(&self) -> Vec<Span> {
+        match (self.spans.is_empty(), self.start, self.end) {
+            (true, Some(start), Some(end)) => vec![Span { start, end }],
+            _ => self.spans.clone(),
+        }
     }
 
-    pub fn end(&self) -> Timestamp {
-        self.end.clone()
+    /// Combined length of all ranges.
+    pub fn duration(&self) -> Timestamp {
+        self.spans()
+            .iter()
+            .fold(Timestamp::default(), |acc, s| acc + s.duration())
+    }
+
+    pub fn language(&self) -> &Language {
+        &self.language
+    }
+
+    /// Replaces an automatic language with the detected one.
+    pub fn set_language(&mut self, language: Language) -> Result<()> {
+        self.language = language;
+        self.persist(doc! {"language": to_bson(&self.language)?})
     }
 
     pub fn get_file(&self) -> String {
         self.file.to_string()
     }
 
-    pub fn to_entry(&self, id: Id, text: String) -> Result<Entry> {
-        let duration = self.end - self.start;
+    pub fn profile(&self) -> Result<Profile> {
+        Profile::get(&self.profile)
+    }
+
+    pub fn accurate(&self) -> bool {
+        self.accurate
+    }
+
+    pub fn set_cut(&mut self, cut: CutMode) -> Result<()> {
+        self.cut = cut;
+        self.persist(doc! {"cut": to_bson(&cut)?})
+    }
+
+    pub fn state(&self) -> JobState {
+        self.state
+    }
+
+    pub fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn id(&self) -> Option<Id> {
+        self.id.clone()
+    }
+
+    pub fn passes(&self) -> u8 {
+        self.passes
+    }
+
+    /// Returns the id of the job, allocating and storing one on first use.
+    pub fn assign(&mut self) -> Result<Id> {
+        if let Some(id) = &self.id {
+            return Ok(id.clone());
+        }
+        let id = Id::next()?;
+        self.id = Some(id.clone());
+        self.persist(doc! {"id": id.get()})?;
+        Ok(id)
+    }
+
+    /// Records the number of finished passes.
+    pub fn checkpoint(&mut self, passes: u8) -> Result<()> {
+        self.passes = passes;
+        self.persist(doc! {"passes": passes as i32})
+    }
+
+    /// Starts a new attempt, clearing the previous error.
+    pub fn attempt(&mut self) -> Result<()> {
+        self.attempts += 1;
+        self.error = None;
+        self.state = JobState::Cutting;
+        self.persist(doc! {"attempts": self.attempts, "error": Bson::Null})
+    }
+
+    pub fn transition(&mut self, state: JobState) -> Result<()> {
+        self.state = state;
+        self.persist(doc! {"done": state == JobState::Done})
+    }
+
+    pub fn fail(&mut self, error: String) -> Result<()> {
+        self.state = JobState::Failed;
+        self.error = Some(error.clone());
+        self.persist(doc! {"error": error})
+    }
+
+    /// Marks the job as cancelled, its temp folder is gone so all passes rerun.
+    pub fn cancel(&mut self) -> Result<()> {
+        self.state = JobState::Cancelled;
+        self.passes = 0;
+        self.persist(doc! {"passes": 0})
+    }
+
+    /// Requeues a failed or cancelled job.
+    pub fn retry(&mut self) -> Result<()> {
+        self.state = JobState::Queued;
+        self.error = None;
+        self.persist(doc! {"error": Bson::Null})
+    }
+
+    // Writes the state and timestamp together with the given changes
+    fn persist(&mut self, mut changes: Document) -> Result<()> {
+        self.updated = now();
+        changes.insert("state", to_bson(&self.state)?);
+        changes.insert("updated", to_bson(&self.updated)?);
+        DATABASE.update_many::<Job>(doc! {"uid": self.uid.clone()}, doc! {"$set": changes})
+    }
+
+    pub fn to_entry(&self, id: Id, text: String, segments: Vec<Segment>) -> Result<Entry> {
+        let duration = self.duration();
         Entry::new(
             id,
             &self.file,
@@ -187,6 +367,9 @@ impl Job {
             self.date,
             self.tags.clone(),
             duration,
+            self.cut,
+            self.language,
+            segments,
         )
     }
 }
@@ -0,0 +1,201 @@
+//! # Language
+//!
+//! Spoken language of a clip, limited to the ISO 639-1 codes whisper knows.
+
+use std::fmt::{self, Display};
+
+use anyhow::{anyhow, Result};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+/// Code and name of every language whisper can transcribe
+const LANGUAGES: [(&str, &str); 97] = [
+    ("en", "english"),
+    ("zh", "chinese"),
+    ("de", "german"),
+    ("es", "spanish"),
+    ("ru", "russian"),
+    ("ko", "korean"),
+    ("fr", "french"),
+    ("ja", "japanese"),
+    ("pt", "portuguese"),
+    ("tr", "turkish"),
+    ("pl", "polish"),
+    ("ca", "catalan"),
+    ("nl", "dutch"),
+    ("ar", "arabic"),
+    ("sv", "swedish"),
+    ("it", "italian"),
+    ("id", "indonesian"),
+    ("hi", "hindi"),
+    ("fi", "finnish"),
+    ("vi", "vietnamese"),
+    ("he", "hebrew"),
+    ("uk", "ukrainian"),
+    ("el", "greek"),
+    ("ms", "malay"),
+    ("cs", "czech"),
+    ("ro", "romanian"),
+    ("da", "danish"),
+    ("hu", "hungarian"),
+    ("ta", "tamil"),
+    ("no", "norwegian"),
+    ("th", "thai"),
+    ("ur", "urdu"),
+    ("hr", "croatian"),
+    ("bg", "bulgarian"),
+    ("lt", "lithuanian"),
+    ("la", "latin"),
+    ("mi", "maori"),
+    ("ml", "malayalam"),
+    ("cy", "welsh"),
+    ("sk", "slovak"),
+    ("te", "telugu"),
+    ("fa", "persian"),
+    ("lv", "latvian"),
+    ("bn", "bengali"),
+    ("sr", "serbian"),
+    ("az", "azerbaijani"),
+    ("sl", "slovenian"),
+    ("kn", "kannada"),
+    ("et", "estonian"),
+    ("mk", "macedonian"),
+    ("br", "breton"),
+    ("eu", "basque"),
+    ("is", "icelandic"),
+    ("hy", "armenian"),
+    ("ne", "nepali"),
+    ("mn", "mongolian"),
+    ("bs", "bosnian"),
+    ("kk", "kazakh"),
+    ("sq", "albanian"),
+    ("sw", "swahili"),
+    ("gl", "galician"),
+    ("mr", "marathi"),
+    ("pa", "punjabi"),
+    ("si", "sinhala"),
+    ("km", "khmer"),
+    ("sn", "shona"),
+    ("yo", "yoruba"),
+    ("so", "somali"),
+    ("af", "afrikaans"),
+    ("oc", "occitan"),
+    ("ka", "georgian"),
+    ("be", "belarusian"),
+    ("tg", "tajik"),
+    ("sd", "sindhi"),
+    ("gu", "gujarati"),
+    ("am", "amharic"),
+    ("yi", "yiddish"),
+    ("lo", "lao"),
+    ("uz", "uzbek"),
+    ("fo", "faroese"),
+    ("ht", "haitian creole"),
+    ("ps", "pashto"),
+    ("tk", "turkmen"),
+    ("nn", "nynorsk"),
+    ("mt", "maltese"),
+    ("sa", "sanskrit"),
+    ("lb", "luxembourgish"),
+    ("my", "myanmar"),
+    ("bo", "tibetan"),
+    ("tl", "tagalog"),
+    ("mg", "malagasy"),
+    ("as", "assamese"),
+    ("tt", "tatar"),
+    ("ln", "lingala"),
+    ("ha", "hausa"),
+    ("ba", "bashkir"),
+    // whisper uses jw instead of jv
+    ("jw", "javanese"),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Language {
+    /// Detected by the transcriber
+    #[default]
+    Auto,
+    /// ISO 639-1 code
+    Iso(&'static str),
+}
+
+impl Language {
+    /// Accepts codes and English names in any case, empty input is `Auto`.
+    pub fn from_str(s: &str) -> Result<Language> {
+        let s = s.trim().to_lowercase();
+        if s.is_empty() || s == "auto" {
+            return Ok(Self::Auto);
+        }
+        LANGUAGES
+            .iter()
+            .find(|(code, name)| *code == s || *name == s)
+            .map(|(code, _)| Self::Iso(code))
+            .ok_or(anyhow!("unknown language {}", s))
+    }
+
+    pub fn from_input(lines: &[String]) -> Result<Language> {
+        match lines.first() {
+            Some(l) => Self::from_str(l),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// ISO 639-1 code, `None` if the language should be detected.
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            Language::Auto => None,
+            Language::Iso(code) => Some(code),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::Auto => "auto",
+            Language::Iso(code) => LANGUAGES
+                .iter()
+                .find(|(c, _)| c == code)
+                .map(|(_, name)| *name)
+                .unwrap_or(code),
+        }
+    }
+}
+
+impl Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name();
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(c) => write!(f, "{}{}", c.to_uppercase(), chars.as_str()),
+            None => Ok(()),
+        }
+    }
+}
+
+// Stored as the code, older jobs used the upper case variant names
+impl Serialize for Language {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code().unwrap_or("auto"))
+    }
+}
+
+impl<'de> Deserialize<'de> for Language {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_input() {
+        assert_eq!(Language::from_str("DE").unwrap(), Language::Iso("de"));
+        assert_eq!(
+            Language::from_str(" English").unwrap().to_string(),
+            "English"
+        );
+        assert_eq!(Language::from_str("").unwrap(), Language::Auto);
+        assert!(Language::from_str("klingon").is_err());
+    }
+}
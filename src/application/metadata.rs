@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 
 use std::process::Command;
 
+use super::timestamp::Timestamp;
+
 #[derive(Deserialize, Debug, Serialize, Clone)]
 pub struct Metadata {
     streams: Vec<Stream>,
@@ -11,11 +13,23 @@ pub struct Metadata {
 
 impl Metadata {
     pub fn new(path: String) -> Result<Self> {
-        let s = Self::generate(path);
+        let s = Self::generate(path)?;
         Ok(serde_json::from_str(&s)?)
     }
 
-    fn generate(path: String) -> String {
+    /// First video stream
+    pub fn video(&self) -> Option<&Stream> {
+        self.streams.iter().find(|s| s.codec_type == Codec::Video)
+    }
+
+    pub fn audio_streams(&self) -> usize {
+        self.streams
+            .iter()
+            .filter(|s| s.codec_type == Codec::Audio)
+            .count()
+    }
+
+    fn generate(path: String) -> Result<String> {
         let cmd = Command::new("ffprobe")
             .args(&[
                 "-print_format",
@@ -24,16 +38,42 @@ impl Metadata {
                 "-show_streams",
                 &path,
             ])
-            .output()
-            .expect(" Unable to generate metadata. ");
+            .output()?;
 
         let out = cmd.stdout;
 
         let s = String::from_utf8_lossy(&out);
 
-        s.to_string()
+        Ok(s.to_string())
     }
 }
+/// Keyframe timestamps of the first video stream around the given range.
+pub fn keyframes(path: &str, from: Timestamp, to: Timestamp) -> Result<Vec<Timestamp>> {
+    let cmd = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-skip_frame",
+            "nokey",
+            "-show_entries",
+            "frame=pts_time",
+            "-of",
+            "csv=p=0",
+            "-read_intervals",
+            &format!("{}%{}", from.to_string(), to.to_string()),
+            path,
+        ])
+        .output()?;
+
+    let out = String::from_utf8_lossy(&cmd.stdout);
+    Ok(out
+        .lines()
+        .filter_map(|l| Timestamp::from_secs(l.trim_matches(',')).ok())
+        .collect())
+}
+
 impl ToString for Metadata {
     fn to_string(&self) -> String {
         let mut top = format!(
@@ -63,6 +103,7 @@ pub struct Stream {
     width: usize,
     height: usize,
     codec_type: Codec,
+    codec_name: String,
     codec_long_name: String,
     bit_rate: String,
     channels: usize,
@@ -96,12 +137,21 @@ impl ToString for Stream {
     }
 }
 
+impl Stream {
+    pub fn codec(&self) -> &str {
+        &self.codec_name
+    }
+}
+
 #[derive(Deserialize, Debug, Serialize, Eq, PartialEq, Clone)]
 pub enum Codec {
     #[serde(rename(deserialize = "video"))]
     Video,
     #[serde(rename(deserialize = "audio"))]
     Audio,
+    // Subtitles, data and attachments
+    #[serde(other)]
+    Other,
 }
 
 impl Default for Codec {
@@ -6,17 +6,28 @@ use crate::interface::Render;
 use crossterm::event::KeyEvent;
 
 pub mod actions;
+pub mod check;
+pub mod cut;
 pub mod entry;
+pub mod export;
 mod filelist;
+pub mod hit;
 pub mod id;
 pub mod indexed;
 pub mod job;
+pub mod language;
 pub mod metadata;
 mod parse_date;
 pub mod processor;
+pub mod profile;
+pub mod queue;
+pub mod recovery;
+pub mod segment;
 pub mod status;
 pub mod tag;
 mod timestamp;
+pub mod transcriber;
+pub mod trash;
 
 pub use entry::Entry;
 pub use filelist::load;
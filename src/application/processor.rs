@@ -2,149 +2,338 @@
 
 use polodb_core::bson::doc;
 
-use std::io::{BufRead, BufReader};
-use std::process::{Command, Stdio};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::JoinHandle;
 use std::time::Duration;
 use std::{fs, thread};
 
+use super::cut::{self, CutMode, Part};
 use super::id::Id;
-use super::job::Job;
+use super::job::{Job, JobState};
+use super::language::Language;
+use super::segment::Segment;
 use super::status::Status;
 use super::timestamp::Timestamp;
+use super::transcriber;
+use super::{Entry, Metadata};
 
 use anyhow::{anyhow, Result};
 
 use crate::DATABASE;
 
-pub fn execute(job: Job) -> Receiver<Status> {
-    let (sender, receiver) = channel();
+/// Handle to a running job, used to kill its child processes.
+#[derive(Debug, Clone, Default)]
+pub struct Control {
+    cancelled: Arc<AtomicBool>,
+    // Stopped for shutdown, the job resumes on the next start
+    interrupted: Arc<AtomicBool>,
+    child: Arc<Mutex<Option<Child>>>,
+}
+
+impl Control {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        if let Some(child) = self.lock().as_mut() {
+            let _ = child.kill();
+        }
+    }
+
+    /// Kills the child processes but keeps the state and checkpoints of the job.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+        self.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
 
-    let id = Id::default();
+    pub fn is_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
 
-    let _ = thread::spawn(move || {
-        if let Ok(_) = first_pass(id.clone(), job.clone(), sender.clone()) {
-            if let Ok(t) = second_pass(id.clone(), job.clone(), sender.clone()) {
-                let _ = third_pass(id.clone(), job, sender.clone(), t);
-                let _ = sender.send(Status::Complete(id));
-            }
+    // Registers the running child process, killed right away if already cancelled
+    pub(super) fn attach(&self, mut child: Child) {
+        let mut slot = self.lock();
+        if self.is_cancelled() {
+            let _ = child.kill();
+        }
+        *slot = Some(child);
+    }
+
+    pub(super) fn detach(&self) -> Result<Child> {
+        self.lock().take().ok_or(anyhow!("child process missing"))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Child>> {
+        self.child.lock().expect("Control poisoned")
+    }
+}
+
+pub fn execute(mut job: Job, control: Control) -> Receiver<Status> {
+    let (sender, receiver) = channel();
+
+    let _ = thread::spawn(move || match run(&mut job, sender.clone(), &control) {
+        Ok(id) => {
+            let _ = sender.send(Status::Complete(id));
+        }
+        // Left as it is so the next start resumes from the checkpoint
+        Err(_) if control.is_interrupted() => {}
+        Err(_) if control.is_cancelled() => {
+            let _ = cleanup(&job);
+            let _ = job.cancel();
+            let _ = sender.send(Status::Cancelled);
+        }
+        Err(e) => {
+            let msg = format!("{:#}", e);
+            let _ = job.fail(msg.clone());
+            let _ = sender.send(Status::Failed(msg));
         }
     });
 
     receiver
 }
 
-fn first_pass(id: Id, job: Job, snd: Sender<Status>) -> Result<()> {
-    let _ = snd.send(Status::First(0)).unwrap();
+fn run(job: &mut Job, snd: Sender<Status>, ctl: &Control) -> Result<Id> {
+    let id = job.assign()?;
+    let resume = checkpoint(&id, job.passes())?;
+    job.attempt()?;
+
+    if resume < 1 {
+        let mode = first_pass(id.clone(), job.clone(), snd.clone(), ctl)?;
+        job.set_cut(mode)?;
+        job.checkpoint(1)?;
+    } else {
+        let _ = snd.send(Status::First(100));
+    }
+
+    job.transition(JobState::Transcribing)?;
+    let text = if resume < 2 {
+        let (text, language) = second_pass(id.clone(), job.clone(), snd.clone(), ctl)?;
+        if language != *job.language() {
+            job.set_language(language)?;
+        }
+        job.checkpoint(2)?;
+        text
+    } else {
+        let _ = snd.send(Status::Second(100));
+        fs::read_to_string(id.text_path()?)?
+    };
+
+    if ctl.is_cancelled() {
+        return Err(anyhow!("cancelled"));
+    }
+
+    job.transition(JobState::Finalizing)?;
+    third_pass(id.clone(), job.clone(), snd, text)?;
+    job.transition(JobState::Done)?;
+    Ok(id)
+}
+
+// Removes the temp folder of a cancelled job
+fn cleanup(job: &Job) -> Result<()> {
+    if let Some(id) = job.id() {
+        fs::remove_dir_all(id.temp_dir()?)?;
+    }
+    Ok(())
+}
+
+// Verifies the recorded checkpoint against the files left in the temp folder
+fn checkpoint(id: &Id, passes: u8) -> Result<u8> {
+    let cut = Path::new(&id.temp_path()?).exists();
+    let text = Path::new(&id.text_path()?).exists() && Path::new(&id.srt_path()?).exists();
+    Ok(match passes {
+        p if p >= 2 && cut && text => 2,
+        p if p >= 1 && cut => 1,
+        _ => 0,
+    })
+}
+
+// Returns the mode used for cutting
+fn first_pass(id: Id, job: Job, snd: Sender<Status>, ctl: &Control) -> Result<CutMode> {
+    let _ = snd.send(Status::First(0));
+
+    let duration = job.duration();
+
+    let profile = job.profile()?;
+    let meta = Metadata::new(job.get_file()).ok();
+    // Recorded as the mode of the range needing the most re-encoding
+    let mut mode = CutMode::default();
+    let mut parts = Vec::new();
+    for span in job.spans() {
+        let (m, p) = cut::plan(
+            &job.get_file(),
+            span.start,
+            span.end,
+            &profile,
+            meta.as_ref(),
+            job.accurate(),
+        );
+        mode = mode.max(m);
+        parts.extend(p);
+    }
+
+    if let [part] = parts.as_slice() {
+        cut_part(
+            &job,
+            part,
+            &id.temp_path()?,
+            Timestamp::default(),
+            duration,
+            &snd,
+            ctl,
+        )?;
+    } else {
+        let mut offset = Timestamp::default();
+        let mut list = String::new();
+        for (i, part) in parts.iter().enumerate() {
+            let out = id.part_path(i)?;
+            cut_part(&job, part, &out, offset, duration, &snd, ctl)?;
+            offset = offset + part.duration;
+            list += &format!("file '{}'\n", out);
+        }
+        fs::write(id.concat_path()?, list)?;
+        concat(&id, ctl)?;
+    }
 
-    let duration = job.end() - job.start();
+    let _ = snd.send(Status::First(100));
+    Ok(mode)
+}
 
+// Runs ffmpeg for one part, progress is relative to the whole clip
+fn cut_part(
+    job: &Job,
+    part: &Part,
+    out: &str,
+    offset: Timestamp,
+    total: Timestamp,
+    snd: &Sender<Status>,
+    ctl: &Control,
+) -> Result<()> {
     let pipe = Stdio::piped();
     let err = Stdio::piped();
 
     let mut cmd = Command::new("ffmpeg")
+        .arg("-y")
         .arg("-hide_banner")
         .arg("-v")
-        .arg("quiet")
-        .arg("-stats")
+        .arg("error")
+        .arg("-nostats")
         .arg("-ss")
-        .arg(job.start().to_string())
+        .arg(part.start.to_string())
         .arg("-i")
         .arg(job.get_file())
-        .arg("-c:v")
-        .arg("copy")
-        .arg("-c:a")
-        .arg("aac")
-        .arg("-filter_complex")
-        .arg("amerge=inputs=2")
-        .arg("-crf")
-        .arg("10")
+        .args(&part.args)
         .arg("-t")
-        .arg(duration.to_string())
+        .arg(part.duration.to_string())
         .arg("-progress")
         .arg("/dev/stdout")
-        .arg(id.temp_path().unwrap())
+        .arg(out)
         .stdout(pipe)
         .stderr(err)
-        .spawn()
-        .unwrap();
+        .spawn()?;
 
-    let stdout = cmd.stdout.as_mut().unwrap();
+    let errors = collect_stderr(&mut cmd);
+    let stdout = cmd.stdout.take().ok_or(anyhow!("no ffmpeg output"))?;
+    ctl.attach(cmd);
     let reader = BufReader::new(stdout);
     let lines = reader.lines();
 
-    for line in lines {
-        let times = match get_timestamp(line.unwrap()) {
-            Some(t) => t,
-            None => String::new(),
-        };
+    for line in lines.map_while(Result::ok) {
+        let times = get_timestamp(line).unwrap_or_default();
         if let Ok(ts) = Timestamp::from_str(times) {
-            let perc = ts / duration;
+            let perc = (offset + ts) / total;
             let parsed = parse_percentage(perc);
             let s = Status::First(parsed);
-            let _ = snd.send(s).unwrap();
+            let _ = snd.send(s);
         }
     }
 
-    let code = cmd.wait().unwrap();
-    if code.success() {
-        let _ = snd.send(Status::First(100));
-        Ok(())
-    } else {
-        Err(anyhow!("pass 1 failed"))
-    }
+    check(ctl.detach()?, errors, "pass 1")
 }
 
-// Returns transcript string
-fn second_pass(id: Id, job: Job, snd: Sender<Status>) -> Result<String> {
-    let _ = snd.send(Status::Second(0)).unwrap();
-
-    let duration = job.end() - job.start();
-
-    let mut cmd = Command::new("whisper")
-        .arg(id.temp_path().unwrap())
-        .arg("--language")
-        .arg("German")
-        .arg("--model")
-        .arg("medium")
-        .arg("-o")
-        .arg(id.temp_dir()?)
-        .stdout(Stdio::piped())
+// Joins all parts without re-encoding
+fn concat(id: &Id, ctl: &Control) -> Result<()> {
+    let mut cmd = Command::new("ffmpeg")
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-v")
+        .arg("error")
+        .arg("-f")
+        .arg("concat")
+        .arg("-safe")
+        .arg("0")
+        .arg("-i")
+        .arg(id.concat_path()?)
+        .arg("-c")
+        .arg("copy")
+        .arg(id.temp_path()?)
+        .stdout(Stdio::null())
         .stderr(Stdio::piped())
-        .spawn()
-        .unwrap();
+        .spawn()?;
 
-    let stdout = cmd.stdout.as_mut().unwrap();
-    let reader = BufReader::new(stdout);
-    let lines = reader.lines();
+    let errors = collect_stderr(&mut cmd);
+    ctl.attach(cmd);
+    check(ctl.detach()?, errors, "pass 1 (concat)")
+}
+
+// Returns transcript string and the spoken language
+fn second_pass(id: Id, job: Job, snd: Sender<Status>, ctl: &Control) -> Result<(String, Language)> {
+    let _ = snd.send(Status::Second(0));
+
+    let duration = job.duration();
+    let transcriber = transcriber::configured()?;
 
     for i in 1..10 {
         let _ = snd.send(Status::Second(i as u16));
         thread::sleep(Duration::from_millis(125));
     }
 
-    for (_i, line) in lines.enumerate() {
-        // println!("LINE: {:?}", line);
-        if line.is_err() {
-            continue;
-        }
-        let line = line.unwrap();
-        if let Some(ts) = get_segment(&line) {
-            let perc = ts / duration;
-            let parsed = parse_percentage(perc);
-            // stupid UI enhancement
-            if parsed <= 12 {
-                continue;
-            }
+    let language = transcriber.transcribe(&id, job.language(), ctl, &mut |ts| {
+        let perc = ts / duration;
+        let parsed = parse_percentage(perc);
+        // stupid UI enhancement
+        if parsed > 12 {
             let _ = snd.send(Status::Second(parsed));
         }
-    }
+    })?;
 
     let f = std::fs::read_to_string(id.text_path()?)?;
     let _ = snd.send(Status::Second(100));
-    // println!("{:?}", f);
 
-    Ok(f)
+    Ok((f, language))
+}
+
+// Reads stderr on a separate thread so a full pipe can't block the child
+pub(super) fn collect_stderr(cmd: &mut Child) -> JoinHandle<String> {
+    let stderr = cmd.stderr.take();
+    thread::spawn(move || {
+        let mut out = String::new();
+        if let Some(mut e) = stderr {
+            let _ = e.read_to_string(&mut out);
+        }
+        out
+    })
+}
+
+pub(super) fn check(cmd: Child, errors: JoinHandle<String>, pass: &str) -> Result<()> {
+    finish(cmd, errors, pass).map(|_| ())
+}
+
+// Like check, returning stderr of a successful run
+pub(super) fn finish(mut cmd: Child, errors: JoinHandle<String>, pass: &str) -> Result<String> {
+    let code = cmd.wait()?;
+    let errors = errors.join().unwrap_or_default();
+    if code.success() {
+        Ok(errors)
+    } else {
+        Err(anyhow!("{} failed ({}): {}", pass, code, errors.trim()))
+    }
 }
 
 fn third_pass(id: Id, job: Job, snd: Sender<Status>, text: String) -> Result<()> {
@@ -157,7 +346,8 @@ fn third_pass(id: Id, job: Job, snd: Sender<Status>, text: String) -> Result<()>
 
     let _ = snd.send(Status::Third(0))?;
 
-    let entry = job.to_entry(id.clone(), text)?;
+    let segments = Segment::parse_srt(&fs::read_to_string(id.srt_path()?)?);
+    let entry = job.to_entry(id.clone(), text, segments)?;
     let from = id.temp_path()?;
     let to = id.data_path()?;
 
@@ -174,16 +364,20 @@ fn third_pass(id: Id, job: Job, snd: Sender<Status>, text: String) -> Result<()>
 
     let _ = snd.send(Status::Third(50))?;
 
-    let json = serde_json::to_string_pretty(&entry)?;
-    let jpath = id.meta_path()?;
-    let _ = fs::write(jpath, json)?;
+    entry.write_meta()?;
 
     let _ = snd.send(Status::Third(75))?;
 
-    let _ = DATABASE.insert_indexed(entry);
+    // A previous attempt might have crashed after inserting
+    if DATABASE.get_one::<Entry>(doc! {"_id": id.get()}).is_err() {
+        DATABASE.insert_indexed(entry)?;
+    }
 
     let _ = snd.send(Status::Third(80))?;
 
+    // Remaining whisper outputs
+    let _ = fs::remove_dir_all(id.temp_dir()?);
+
     // check if source exists
     // let source = format!("{}/source/{}", ROOT.as_str(), job.get_file());
     // if let Ok(_f) = File::open(&source) {
@@ -193,14 +387,6 @@ fn third_pass(id: Id, job: Job, snd: Sender<Status>, text: String) -> Result<()>
     //     let _ = fs::write(from, source)?;
     // }
 
-    // mark job as done
-    let _ = DATABASE.update_many::<Job>(
-        doc! {"uid": job.uid},
-        doc! {
-            "$set": doc! {"done": true}
-        },
-    )?;
-
     Ok(())
 }
 
@@ -219,52 +405,3 @@ fn get_timestamp(line: String) -> Option<String> {
     // let p = Timestamp::from_str(parts.get(1).unwrap());
     return Some(parts.get(1).unwrap().to_string());
 }
-
-fn get_segment(line: &str) -> Option<Timestamp> {
-    let first = line.split(" ").nth(0)?;
-    let mut found = String::from("00:");
-    for (i, part) in first.split(":").enumerate() {
-        match i {
-            0 => {
-                // minutes
-                if let Some(c) = part.chars().nth(1) {
-                    if c.is_ascii_digit() {
-                        // pure perfection
-                        found += part.split("[").nth(1).unwrap_or("");
-                    }
-                }
-            }
-            1 => {
-                if let Some(scd) = part.split(".").nth(0) {
-                    found += &format!(":{}", scd);
-                }
-                if let Some(milis) = part.split(".").nth(1) {
-                    found += &format!(".{}", milis);
-                } else {
-                    found += ".00";
-                }
-            }
-            _ => {}
-        }
-    }
-
-    if let Ok(t) = Timestamp::from_str(found) {
-        Some(t)
-    } else {
-        None
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_whisper_timestamp() {
-        let line = "[00:37.000 --> 00:38.000]  Ich komme bei.";
-        assert_eq!(
-            get_segment(line).unwrap(),
-            Timestamp::from_str("00:00:37.000").unwrap()
-        );
-    }
-}
@@ -0,0 +1,149 @@
+//! # Profile
+//!
+//! Named ffmpeg encoding settings for the cut pass.
+
+use anyhow::Result;
+use polodb_core::bson::doc;
+use serde::{Deserialize, Serialize};
+
+use super::Metadata;
+use crate::{store::Entity, DATABASE};
+
+pub const DEFAULT: &str = "copy";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Profile {
+    #[serde(rename = "_id")]
+    name: String,
+    description: String,
+    // Output codec arguments, the audio merge filter is added per source
+    args: Vec<String>,
+    // Keep the video stream
+    video: bool,
+}
+
+impl Profile {
+    fn new(name: &str, description: &str, args: &[&str], video: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            video,
+        }
+    }
+
+    fn defaults() -> Vec<Self> {
+        vec![
+            Self::new(
+                "copy",
+                "Stream copy video, AAC audio",
+                &["-c:v", "copy", "-c:a", "aac"],
+                true,
+            ),
+            Self::new(
+                "x265",
+                "x265 CRF 18, medium preset",
+                &[
+                    "-c:v", "libx265", "-crf", "18", "-preset", "medium", "-c:a", "aac",
+                ],
+                true,
+            ),
+            Self::new(
+                "x264-fast",
+                "x264 CRF 23, fast preset",
+                &[
+                    "-c:v", "libx264", "-crf", "23", "-preset", "fast", "-c:a", "aac",
+                ],
+                true,
+            ),
+            Self::new("audio", "Audio only, AAC", &["-vn", "-c:a", "aac"], false),
+        ]
+    }
+
+    /// Inserts the default profiles into an empty collection.
+    fn ensure_configured() -> Result<()> {
+        if DATABASE.get_all::<Self>()?.next().is_none() {
+            for p in Self::defaults() {
+                DATABASE.insert(p)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn all() -> Result<Vec<Self>> {
+        Self::ensure_configured()?;
+        Ok(DATABASE.get_all::<Self>()?.filter_map(Result::ok).collect())
+    }
+
+    pub fn get(name: &str) -> Result<Self> {
+        Self::ensure_configured()?;
+        DATABASE.get_one(doc! {"_id": name})
+    }
+
+    /// Picks a profile based on the streams reported by ffprobe.
+    pub fn select(meta: &Metadata) -> &'static str {
+        match meta.video() {
+            None => "audio",
+            Some(v) if matches!(v.codec(), "h264" | "hevc") => "copy",
+            Some(_) => "x265",
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn has_video(&self) -> bool {
+        self.video
+    }
+
+    /// Copies the video stream without re-encoding.
+    pub fn stream_copy(&self) -> bool {
+        self.video
+            && self
+                .args
+                .windows(2)
+                .any(|w| w[0] == "-c:v" && w[1] == "copy")
+    }
+
+    /// Output arguments with the video codec replaced by `video`.
+    pub fn encode_args(&self, audio_streams: usize, video: &[&str]) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut itr = self.args(audio_streams).into_iter();
+        while let Some(arg) = itr.next() {
+            if arg == "-c:v" {
+                let _ = itr.next();
+                args.extend(video.iter().map(|v| v.to_string()));
+            } else {
+                args.push(arg);
+            }
+        }
+        args
+    }
+
+    /// ffmpeg output arguments, merging all audio tracks into one.
+    pub fn args(&self, audio_streams: usize) -> Vec<String> {
+        let mut args = self.args.clone();
+        if audio_streams > 1 {
+            args.push("-filter_complex".to_string());
+            args.push(format!("amerge=inputs={}", audio_streams));
+            args.push("-ac".to_string());
+            args.push("2".to_string());
+        }
+        args
+    }
+}
+
+impl Entity for Profile {
+    fn collection() -> &'static str {
+        "profiles"
+    }
+
+    fn to_document(&self, _schema: &tantivy::schema::Schema) -> Result<tantivy::Document> {
+        panic!("DO NOT PUT PROFILES INTO FTS");
+    }
+}
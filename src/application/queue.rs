@@ -0,0 +1,160 @@
+//! # Queue
+//!
+//! Background worker draining all pending jobs, independent of the UI state.
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use polodb_core::bson::doc;
+
+use super::job::Job;
+use super::processor::{self, Control};
+use super::status::Status;
+use crate::DATABASE;
+
+#[derive(Debug, Default)]
+pub struct Queue {
+    state: Arc<Mutex<Progress>>,
+}
+
+/// Snapshot of the worker state
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    pub running: bool,
+    pub current: Option<Job>,
+    pub status: Status,
+    // Number of jobs processed since startup
+    pub finished: usize,
+    // Explicitly requested jobs, run before the rest
+    queued: VecDeque<Job>,
+    // Handle of the current job
+    control: Control,
+    // Don't pick up any further jobs
+    stopping: bool,
+    // Jobs attempted by the running worker
+    seen: HashSet<String>,
+}
+
+impl Queue {
+    /// Starts the worker if it isn't running yet, `first` is executed next.
+    /// A requested job runs again even if the worker already attempted it.
+    pub fn start(&self, first: Option<Job>) {
+        let mut state = self.lock();
+        if let Some(job) = first {
+            state.seen.remove(&job.uid);
+            state.queued.push_back(job);
+        }
+        state.stopping = false;
+        if state.running {
+            return;
+        }
+        state.running = true;
+        state.seen.clear();
+
+        let shared = self.state.clone();
+        let _ = thread::spawn(move || work(shared));
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.lock().clone()
+    }
+
+    /// Cancels the current job, the worker continues with the next one.
+    pub fn cancel(&self) {
+        self.lock().control.cancel();
+    }
+
+    /// Interrupts the current job and waits shortly for the worker to stop.
+    /// The job keeps its state and resumes on the next start.
+    pub fn shutdown(&self) {
+        {
+            let mut state = self.lock();
+            state.stopping = true;
+            state.control.interrupt();
+        }
+        let start = Instant::now();
+        while self.lock().running && start.elapsed() < Duration::from_secs(3) {
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Progress> {
+        self.state.lock().expect("Queue poisoned")
+    }
+}
+
+// Runs every pending job once. Jobs from the database are only attempted once
+// per run so failing jobs can't loop forever, requested ones are skipped if
+// they already ran unless they were requested again.
+fn work(shared: Arc<Mutex<Progress>>) {
+    let lock = || shared.lock().expect("Queue poisoned");
+    loop {
+        let (next, seen) = {
+            let mut state = lock();
+            if state.stopping {
+                break;
+            }
+            (next_queued(&mut state), state.seen.clone())
+        };
+        let job = match next.or_else(|| pending(&seen)) {
+            Some(j) => j,
+            None => {
+                // Checked together with `running` so `start` can't queue a job in between
+                let mut state = lock();
+                let Progress { queued, seen, .. } = &mut *state;
+                queued.retain(|j| !seen.contains(&j.uid));
+                if !state.queued.is_empty() {
+                    continue;
+                }
+                state.running = false;
+                state.stopping = false;
+                return;
+            }
+        };
+        lock().seen.insert(job.uid.clone());
+
+        let control = Control::default();
+        {
+            let mut state = lock();
+            state.current = Some(job.clone());
+            state.status = Status::default();
+            state.control = control.clone();
+        }
+        for status in processor::execute(job, control).iter() {
+            lock().status = status;
+        }
+        let mut state = lock();
+        state.current = None;
+        state.finished += 1;
+    }
+    let mut state = lock();
+    state.running = false;
+    state.stopping = false;
+}
+
+// First requested job which neither ran nor is running, as currently stored
+fn next_queued(state: &mut Progress) -> Option<Job> {
+    while let Some(job) = state.queued.pop_front() {
+        let current = state.current.as_ref().is_some_and(|c| c.uid == job.uid);
+        if current || state.seen.contains(&job.uid) {
+            continue;
+        }
+        // The list it was requested from might be outdated
+        match DATABASE.get_one::<Job>(doc! {"uid": job.uid}) {
+            Ok(job) if job.state().is_runnable() => return Some(job),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Next runnable job which isn't in `seen`.
+pub fn pending(seen: &HashSet<String>) -> Option<Job> {
+    DATABASE
+        .get_many::<Job>(doc! {"done": false})
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|j| j.state().is_runnable() && !seen.contains(&j.uid))
+}
@@ -0,0 +1,143 @@
+//! # Recovery
+//!
+//! Rebuilds the document store and the search index from the meta JSON
+//! written next to every entry. Settings, profiles and jobs only exist in
+//! the database, they are copied from the old one if it can still be read.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use polodb_core::bson::doc;
+
+use super::{job::Job, profile::Profile, tag::Tag, Entry};
+use crate::{DATABASE, ROOT};
+
+/// Outcome of a recovery
+#[derive(Debug, Default)]
+pub struct Recovered {
+    pub entries: usize,
+    // Sidecars which couldn't be read, with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+    pub counter: i64,
+    // Copied from the old database, how many or why they are lost
+    pub kept: Vec<(&'static str, Result<usize, String>)>,
+}
+
+/// Moves the store files into a backup folder, a new store is created when
+/// the database is opened. Has to run before anything uses `DATABASE`.
+pub fn set_aside() -> Result<Option<String>> {
+    let store = format!("{}/store/", ROOT.as_str());
+    let backup = format!("{}broken-{}/", store, Local::now().format("%Y%m%d-%H%M%S"));
+    let mut moved = false;
+    for name in ["CCP.db", "CCP.db.wal"] {
+        let from = format!("{}{}", store, name);
+        if Path::new(&from).exists() {
+            fs::create_dir_all(&backup)?;
+            fs::rename(from, format!("{}{}", backup, name))?;
+            moved = true;
+        }
+    }
+    Ok(moved.then_some(backup))
+}
+
+/// Inserts every entry of the meta folder and indexes them, the id counter
+/// continues after the highest id, including the trashed ones. `backup` is
+/// the folder of the old database.
+pub fn recover(backup: Option<&str>, progress: &mut dyn FnMut(usize, usize)) -> Result<Recovered> {
+    let mut recovered = Recovered::default();
+    if let Some(backup) = backup {
+        recovered.kept = keep(backup);
+    }
+    let mut entries = Vec::new();
+    for path in sidecars(&format!("{}/meta/", ROOT.as_str()))? {
+        match read(&path) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => recovered.skipped.push((path, e.to_string())),
+        }
+    }
+
+    for entry in &entries {
+        for tag in entry.tags() {
+            let _ = Tag::new(&tag.to_string())?;
+        }
+        if DATABASE.get_one::<Entry>(doc! {"_id": entry.id()}).is_err() {
+            DATABASE.insert(entry.clone())?;
+        }
+    }
+    DATABASE.rebuild(&entries, progress)?;
+
+    let trashed: Vec<i64> = sidecars(&format!("{}/trash/", ROOT.as_str()))?
+        .iter()
+        .filter_map(|p| read(p).ok())
+        .map(|e| e.id())
+        .collect();
+    recovered.counter = entries
+        .iter()
+        .map(|e| e.id())
+        .chain(trashed)
+        .max()
+        .unwrap_or_default()
+        .max(DATABASE.get_config()?.get_counter());
+    DATABASE.set_config(doc! {"counter": recovered.counter})?;
+
+    recovered.entries = entries.len();
+    Ok(recovered)
+}
+
+// Copies settings, profiles and jobs, the backup itself is left untouched
+fn keep(backup: &str) -> Vec<(&'static str, Result<usize, String>)> {
+    let copy = format!("{}read/", backup);
+    let opened = copy_store(backup, &copy).and_then(|db| DATABASE.open_backup(&db));
+    let kept = match opened {
+        Ok(old) => vec![
+            ("settings", DATABASE.copy_config_from(&old)),
+            ("profiles", DATABASE.copy_from::<Profile>(&old)),
+            ("jobs", DATABASE.copy_from::<Job>(&old)),
+        ],
+        Err(e) => ["settings", "profiles", "jobs"]
+            .into_iter()
+            .map(|what| (what, Err(anyhow!("{:#}", e))))
+            .collect(),
+    };
+    let _ = fs::remove_dir_all(copy);
+    kept.into_iter()
+        .map(|(what, result)| (what, result.map_err(|e| format!("{:#}", e))))
+        .collect()
+}
+
+// Returns the path of the copied database
+fn copy_store(from: &str, to: &str) -> Result<String> {
+    fs::create_dir_all(to)?;
+    for name in ["CCP.db", "CCP.db.wal"] {
+        let file = format!("{}{}", from, name);
+        if Path::new(&file).exists() {
+            fs::copy(file, format!("{}{}", to, name))?;
+        }
+    }
+    Ok(format!("{}CCP.db", to))
+}
+
+fn read(path: &Path) -> Result<Entry> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+// JSON files one folder below `dir`, sorted
+fn sidecars(dir: &str) -> Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for sub in fs::read_dir(dir)? {
+        let sub = sub?.path();
+        if !sub.is_dir() {
+            continue;
+        }
+        for file in fs::read_dir(sub)? {
+            let file = file?.path();
+            if file.extension().is_some_and(|e| e == "json") {
+                found.push(file);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
@@ -0,0 +1,67 @@
+//! # Segment
+//!
+//! Timed transcript lines, parsed from the SRT written by the transcriber.
+
+use serde::{Deserialize, Serialize};
+
+use super::timestamp::Timestamp;
+use crate::store::Matcher;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Segment {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub text: String,
+}
+
+impl Segment {
+    /// Parses all cues of an SRT file, malformed cues are skipped.
+    pub fn parse_srt(content: &str) -> Vec<Segment> {
+        let content = content.replace("\r\n", "\n");
+        let mut segments = Vec::new();
+        for block in content.split("\n\n") {
+            let mut lines = block.lines().skip_while(|l| !l.contains("-->"));
+            let times = match lines.next() {
+                Some(t) => t,
+                None => continue,
+            };
+            let (start, end) = match times.split_once("-->") {
+                Some((s, e)) => (srt_time(s), srt_time(e)),
+                None => continue,
+            };
+            if let (Some(start), Some(end)) = (start, end) {
+                let text = lines
+                    .map(str::trim)
+                    .filter(|l| !l.is_empty())
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+                segments.push(Segment { start, end, text });
+            }
+        }
+        segments
+    }
+
+    /// Whether a word of the segment matches the searched transcript words.
+    pub fn matches(&self, matcher: &mut Matcher) -> bool {
+        matcher.matches(&self.text)
+    }
+}
+
+// 00:00:01,500
+fn srt_time(s: &str) -> Option<Timestamp> {
+    Timestamp::from_str(s.trim().replace(',', ".")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_srt() {
+        let srt = "1\r\n00:00:00,000 --> 00:00:01,500\r\nHallo Welt\r\n\r\n2\r\n00:00:01,500 --> 00:00:03,000\r\nZweiter\r\nSatz\r\n\r\n";
+        let segments = Segment::parse_srt(srt);
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].end.to_string(), "00:00:01.500");
+        assert_eq!(segments[1].text, "Zweiter Satz");
+    }
+}
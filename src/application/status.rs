@@ -9,6 +9,9 @@ pub enum Status {
     Second(u16),
     Third(u16),
     Complete(Id),
+    /// Error message of the failed pass
+    Failed(String),
+    Cancelled,
 }
 
 impl Status {
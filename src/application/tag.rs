@@ -6,7 +6,10 @@ use polodb_core::bson::doc;
 use rand::thread_rng;
 use serde::{Deserialize, Serialize};
 
-use crate::{store::Entity, DATABASE};
+use crate::{
+    store::{self, Entity},
+    DATABASE,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Tag {
@@ -27,6 +30,26 @@ impl Tag {
             Ok(tag)
         }
     }
+
+    /// Parses tag input, separated by whitespace with ones containing spaces quoted.
+    pub fn parse(lines: &[String]) -> Result<Vec<Self>> {
+        lines
+            .iter()
+            .flat_map(|l| store::words(l))
+            .map(|w| w.trim_matches('"'))
+            .filter(|w| !w.is_empty())
+            .map(Self::new)
+            .collect()
+    }
+}
+
+/// Writes a tag name the way `Tag::parse` reads it back.
+pub fn quote(name: &str) -> String {
+    if name.contains(char::is_whitespace) {
+        format!("\"{}\"", name)
+    } else {
+        name.to_string()
+    }
 }
 
 impl Default for Tag {
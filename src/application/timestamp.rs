@@ -2,7 +2,9 @@
 
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use std::ops::{Div, Sub};
+use std::cmp::Ordering;
+use std::fmt::{self, Display};
+use std::ops::{Add, Div, Sub};
 
 #[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Timestamp {
@@ -16,12 +18,27 @@ impl Sub for Timestamp {
     type Output = Timestamp;
 
     fn sub(self, other: Self) -> Self::Output {
-        Self {
-            hours: self.hours - other.hours,
-            minutes: self.minutes - other.minutes,
-            seconds: self.seconds - other.seconds,
-            millis: self.millis - other.millis,
-        }
+        Self::from_millis(self.to_millis() - other.to_millis())
+    }
+}
+
+impl Add for Timestamp {
+    type Output = Timestamp;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self::from_millis(self.to_millis() + other.to_millis())
+    }
+}
+
+impl PartialOrd for Timestamp {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timestamp {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.to_millis().cmp(&other.to_millis())
     }
 }
 
@@ -36,7 +53,7 @@ impl Div for Timestamp {
 impl ToString for Timestamp {
     fn to_string(&self) -> String {
         format!(
-            "{}:{}:{}.{}",
+            "{:02}:{:02}:{:02}.{:03}",
             self.hours, self.minutes, self.seconds, self.millis
         )
     }
@@ -47,11 +64,25 @@ impl Timestamp {
         3600 * self.hours + 60 * self.minutes + self.seconds
     }
 
-    pub fn from_input(lines: &[String]) -> Result<Self> {
-        for l in lines {
-            return Self::from_str(l);
+    pub fn to_millis(self) -> i64 {
+        1000 * self.to_seconds() as i64 + self.millis as i64
+    }
+
+    /// Normalized timestamp, negative values are clamped to zero.
+    pub fn from_millis(millis: i64) -> Self {
+        let millis = millis.max(0);
+        Self {
+            hours: (millis / 3_600_000) as i32,
+            minutes: (millis / 60_000 % 60) as i32,
+            seconds: (millis / 1000 % 60) as i32,
+            millis: (millis % 1000) as i32,
         }
-        Err(anyhow!("invalid timestamp!"))
+    }
+
+    /// Parses fractional seconds as printed by ffprobe.
+    pub fn from_secs(secs: &str) -> Result<Self> {
+        let secs: f64 = secs.trim().parse()?;
+        Ok(Self::from_millis((secs * 1000.0).round() as i64))
     }
 
     pub fn from_str(line: impl ToString) -> Result<Self> {
@@ -76,7 +107,10 @@ impl Timestamp {
                                 ts.seconds = p.parse()?;
                             }
                             1 => {
-                                ts.millis = p.parse()?;
+                                // Fraction of a second, "5" is 500ms
+                                let digits: String =
+                                    p.chars().chain("000".chars()).take(3).collect();
+                                ts.millis = digits.parse()?;
                             }
                             _ => {
                                 return Err(anyhow!("invalid timestamp!"));
@@ -93,3 +127,78 @@ impl Timestamp {
         Ok(ts)
     }
 }
+
+/// Range of the source file, a job is cut from one or more of them
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Span {
+    pub start: Timestamp,
+    pub end: Timestamp,
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start.to_string(), self.end.to_string())
+    }
+}
+
+impl Span {
+    pub fn duration(&self) -> Timestamp {
+        self.end - self.start
+    }
+
+    /// Pairs up the comma separated start and end times, in order.
+    pub fn from_input(start: &[String], end: &[String]) -> Result<Vec<Self>> {
+        let starts = Self::split(start)?;
+        let ends = Self::split(end)?;
+        if starts.is_empty() || starts.len() != ends.len() {
+            return Err(anyhow!("invalid timestamp!"));
+        }
+        let mut spans = Vec::new();
+        for (start, end) in starts.into_iter().zip(ends) {
+            if end <= start {
+                return Err(anyhow!("range ends before it starts!"));
+            }
+            spans.push(Span { start, end });
+        }
+        Ok(spans)
+    }
+
+    fn split(lines: &[String]) -> Result<Vec<Timestamp>> {
+        lines
+            .iter()
+            .flat_map(|l| l.split([',', ' ']))
+            .filter(|t| !t.trim().is_empty())
+            .map(|t| Timestamp::from_str(t.trim()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sub_normalized() {
+        let start = Timestamp::from_str("00:01:45").unwrap();
+        let end = Timestamp::from_str("00:02:10.5").unwrap();
+        assert_eq!((end - start).to_string(), "00:00:25.500");
+        assert_eq!((start - end).to_millis(), 0);
+    }
+
+    #[test]
+    fn test_from_secs() {
+        let ts = Timestamp::from_secs("62.002000").unwrap();
+        assert_eq!(ts.to_string(), "00:01:02.002");
+    }
+
+    #[test]
+    fn test_span_input() {
+        let start = vec![String::from("00:01:00, 00:05:00")];
+        let end = vec![String::from("00:01:30,00:05:10")];
+        let spans = Span::from_input(&start, &end).unwrap();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1].duration().to_string(), "00:00:10.000");
+        assert!(Span::from_input(&start, &end[..0]).is_err());
+        assert!(Span::from_input(&end, &start).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! # Transcriber
+//!
+//! Speech to text backends of the second pass. Every backend writes the
+//! `.txt` and `.srt` files next to the cut clip in the temp folder.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{anyhow, Result};
+
+use super::id::Id;
+use super::language::Language;
+use super::processor::{check, collect_stderr, finish, Control};
+use super::timestamp::Timestamp;
+use crate::{DATABASE, ROOT};
+
+/// Names accepted in the configuration
+pub const BACKENDS: [&str; 3] = ["whisper", "whisper-cpp", "none"];
+
+pub trait Transcriber {
+    /// Transcribes `Id::temp_path` into `Id::text_path` and `Id::srt_path`,
+    /// `progress` receives the start of every transcribed segment. Returns
+    /// the detected language for `Language::Auto`.
+    fn transcribe(
+        &self,
+        id: &Id,
+        language: &Language,
+        ctl: &Control,
+        progress: &mut dyn FnMut(Timestamp),
+    ) -> Result<Language>;
+}
+
+/// Backend selected in the configuration.
+pub fn configured() -> Result<Box<dyn Transcriber>> {
+    let conf = DATABASE.get_config()?;
+    backend(conf.transcriber(), conf.model())
+}
+
+pub fn backend(name: &str, model: &str) -> Result<Box<dyn Transcriber>> {
+    let model = model.to_string();
+    match name {
+        "whisper" => Ok(Box::new(Whisper { model })),
+        "whisper-cpp" => Ok(Box::new(WhisperCpp { model })),
+        "none" => Ok(Box::new(Disabled)),
+        _ => Err(anyhow!(
+            "unknown transcriber {}, expected one of {}",
+            name,
+            BACKENDS.join(", ")
+        )),
+    }
+}
+
+/// Python whisper CLI
+pub struct Whisper {
+    model: String,
+}
+
+impl Transcriber for Whisper {
+    fn transcribe(
+        &self,
+        id: &Id,
+        language: &Language,
+        ctl: &Control,
+        progress: &mut dyn FnMut(Timestamp),
+    ) -> Result<Language> {
+        let mut cmd = Command::new("whisper");
+        cmd.arg(id.temp_path()?)
+            .arg("--model")
+            .arg(&self.model)
+            .arg("-o")
+            .arg(id.temp_dir()?);
+        // Detected from the first 30 seconds if missing
+        if let Some(code) = language.code() {
+            cmd.arg("--language").arg(code);
+        }
+        let mut cmd = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+
+        let errors = collect_stderr(&mut cmd);
+        let stdout = cmd.stdout.take().ok_or(anyhow!("no whisper output"))?;
+        ctl.attach(cmd);
+
+        let mut detected = *language;
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(name) = line.strip_prefix("Detected language: ") {
+                if *language == Language::Auto {
+                    detected = Language::from_str(name).unwrap_or(detected);
+                }
+            } else if let Some(ts) = get_segment(&line) {
+                progress(ts);
+            }
+        }
+
+        check(ctl.detach()?, errors, "pass 2")?;
+        Ok(detected)
+    }
+}
+
+/// whisper.cpp, only reads 16kHz wav files
+pub struct WhisperCpp {
+    model: String,
+}
+
+impl WhisperCpp {
+    // Bare model names refer to the ggml files in the models folder
+    fn model_path(&self) -> String {
+        if self.model.contains('/') {
+            self.model.clone()
+        } else {
+            format!("{}/models/ggml-{}.bin", ROOT.as_str(), self.model)
+        }
+    }
+}
+
+impl Transcriber for WhisperCpp {
+    fn transcribe(
+        &self,
+        id: &Id,
+        language: &Language,
+        ctl: &Control,
+        progress: &mut dyn FnMut(Timestamp),
+    ) -> Result<Language> {
+        let model = self.model_path();
+        if !Path::new(&model).exists() {
+            return Err(anyhow!("whisper.cpp model {} not found", model));
+        }
+
+        let wav = format!("{}pass_1.wav", id.temp_dir()?);
+        let mut cmd = Command::new("ffmpeg")
+            .arg("-y")
+            .arg("-hide_banner")
+            .arg("-v")
+            .arg("error")
+            .arg("-i")
+            .arg(id.temp_path()?)
+            .arg("-ar")
+            .arg("16000")
+            .arg("-ac")
+            .arg("1")
+            .arg("-c:a")
+            .arg("pcm_s16le")
+            .arg(&wav)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let errors = collect_stderr(&mut cmd);
+        ctl.attach(cmd);
+        check(ctl.detach()?, errors, "pass 2 (wav)")?;
+
+        // Output names are given without extension
+        let out = format!("{}pass_1", id.temp_dir()?);
+        let mut cmd = Command::new("whisper-cli")
+            .arg("-m")
+            .arg(&model)
+            .arg("-l")
+            .arg(language.code().unwrap_or("auto"))
+            .arg("-f")
+            .arg(&wav)
+            .arg("-otxt")
+            .arg("-osrt")
+            .arg("-of")
+            .arg(out)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let errors = collect_stderr(&mut cmd);
+        let stdout = cmd.stdout.take().ok_or(anyhow!("no whisper.cpp output"))?;
+        ctl.attach(cmd);
+
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            // [00:00:00.000 --> 00:00:02.000]  text
+            let start = line
+                .strip_prefix('[')
+                .and_then(|l| l.split(' ').next())
+                .and_then(|t| Timestamp::from_str(t).ok());
+            if let Some(ts) = start {
+                progress(ts);
+            }
+        }
+
+        let log = finish(ctl.detach()?, errors, "pass 2")?;
+        let _ = fs::remove_file(wav);
+
+        // whisper_full_with_state: auto-detected language: de (p = 0.97)
+        let detected = log
+            .lines()
+            .find_map(|l| l.split("auto-detected language: ").nth(1))
+            .and_then(|l| l.split(' ').next())
+            .and_then(|code| Language::from_str(code).ok());
+        match language {
+            Language::Auto => Ok(detected.unwrap_or(*language)),
+            _ => Ok(*language),
+        }
+    }
+}
+
+/// Skips transcription, the entry is only searchable by its metadata
+pub struct Disabled;
+
+impl Transcriber for Disabled {
+    fn transcribe(
+        &self,
+        id: &Id,
+        language: &Language,
+        _ctl: &Control,
+        _progress: &mut dyn FnMut(Timestamp),
+    ) -> Result<Language> {
+        fs::write(id.text_path()?, "")?;
+        fs::write(id.srt_path()?, "")?;
+        Ok(*language)
+    }
+}
+
+fn get_segment(line: &str) -> Option<Timestamp> {
+    let first = line.split(" ").next()?;
+    let mut found = String::from("00:");
+    for (i, part) in first.split(":").enumerate() {
+        match i {
+            0 => {
+                // minutes
+                if let Some(c) = part.chars().nth(1) {
+                    if c.is_ascii_digit() {
+                        // pure perfection
+                        found += part.split("[").nth(1).unwrap_or("");
+                    }
+                }
+            }
+            1 => {
+                if let Some(scd) = part.split(".").next() {
+                    found += &format!(":{}", scd);
+                }
+                if let Some(milis) = part.split(".").nth(1) {
+                    found += &format!(".{}", milis);
+                } else {
+                    found += ".00";
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Timestamp::from_str(found).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whisper_timestamp() {
+        let line = "[00:37.000 --> 00:38.000]  Ich komme bei.";
+        assert_eq!(
+            get_segment(line).unwrap(),
+            Timestamp::from_str("00:00:37.000").unwrap()
+        );
+    }
+}
@@ -0,0 +1,128 @@
+//! # Trash
+//!
+//! Deleted entries are moved to `trash/<id>/` with their media, subtitles
+//! and meta JSON, they can be restored until purged after the retention.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Local};
+
+use super::Entry;
+use crate::{DATABASE, ROOT};
+
+// Written last, marks the entry as completely trashed
+const DELETED: &str = "deleted";
+
+/// Trashed entry with the time it was deleted.
+#[derive(Clone, Debug)]
+pub struct Trashed {
+    pub entry: Entry,
+    pub deleted: DateTime<Local>,
+}
+
+/// Removes the entry from the store and the index and moves its files.
+pub fn delete(entry: &Entry) -> Result<()> {
+    let dir = dir(entry.id());
+    fs::create_dir_all(&dir)?;
+    // Sidecar might be missing or outdated
+    entry.write_meta()?;
+
+    for (from, name) in files(entry)? {
+        if Path::new(&from).exists() {
+            fs::rename(from, format!("{}{}", dir, name))?;
+        }
+    }
+    DATABASE.delete_indexed::<Entry>(entry.id())?;
+    fs::write(format!("{}{}", dir, DELETED), Local::now().to_rfc3339())?;
+
+    Ok(())
+}
+
+/// Moves the files back and adds the entry to the store again.
+pub fn restore(id: i64) -> Result<Entry> {
+    let dir = dir(id);
+    let json = fs::read_to_string(format!("{}{}.json", dir, id))
+        .map_err(|_| anyhow!("entry {} is not in the trash", id))?;
+    let entry: Entry = serde_json::from_str(&json)?;
+
+    for (to, name) in files(&entry)? {
+        let from = format!("{}{}", dir, name);
+        if Path::new(&from).exists() {
+            fs::rename(from, to)?;
+        }
+    }
+    DATABASE.insert_indexed(entry.clone())?;
+    fs::remove_dir_all(dir)?;
+
+    Ok(entry)
+}
+
+/// All trashed entries oldest first, and the folders which couldn't be read.
+pub fn list() -> Result<(Vec<Trashed>, Vec<String>)> {
+    let mut trashed = Vec::new();
+    let mut broken = Vec::new();
+    for dir in fs::read_dir(format!("{}/trash/", ROOT.as_str()))? {
+        let dir = match dir {
+            Ok(d) => d.path(),
+            Err(e) => {
+                broken.push(e.to_string());
+                continue;
+            }
+        };
+        match read(&dir) {
+            Ok(Some(t)) => trashed.push(t),
+            Ok(None) => {}
+            Err(e) => broken.push(format!("{}: {:#}", dir.display(), e)),
+        }
+    }
+    trashed.sort_by_key(|t| t.deleted);
+    Ok((trashed, broken))
+}
+
+/// Removes entries deleted more than `days` ago, returns how many and the
+/// folders which couldn't be read or removed.
+pub fn purge(days: i64) -> Result<(usize, Vec<String>)> {
+    let limit = Local::now() - Duration::days(days);
+    let (trashed, mut broken) = list()?;
+    let mut purged = 0;
+    for t in trashed {
+        if t.deleted < limit {
+            let dir = dir(t.entry.id());
+            match fs::remove_dir_all(&dir) {
+                Ok(()) => purged += 1,
+                Err(e) => broken.push(format!("{}: {}", dir, e)),
+            }
+        }
+    }
+    Ok((purged, broken))
+}
+
+// Trashed entry in `dir`, none if it was interrupted while deleting
+fn read(dir: &Path) -> Result<Option<Trashed>> {
+    let deleted = match fs::read_to_string(dir.join(DELETED)) {
+        Ok(d) => DateTime::parse_from_rfc3339(d.trim())?.with_timezone(&Local),
+        Err(_) => return Ok(None),
+    };
+    let id = dir.file_name().unwrap_or_default().to_string_lossy();
+    let json = fs::read_to_string(dir.join(format!("{}.json", id)))?;
+    Ok(Some(Trashed {
+        entry: serde_json::from_str(&json)?,
+        deleted,
+    }))
+}
+
+fn dir(id: i64) -> String {
+    format!("{}/trash/{}/", ROOT.as_str(), id)
+}
+
+// Location in the library and file name in the trash
+fn files(entry: &Entry) -> Result<[(String, String); 3]> {
+    let id = entry.id();
+    Ok([
+        (entry.out_path()?, format!("{}.mp4", id)),
+        (entry.srt_path()?, format!("{}.srt", id)),
+        (entry.meta_path()?, format!("{}.json", id)),
+    ])
+}
@@ -0,0 +1,456 @@
+//! Command line interface
+//!
+//! Headless subcommands, the TUI is launched if none is given.
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use clap::{Parser, Subcommand};
+use polodb_core::bson::doc;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::application::export::{self, Subtitles};
+use crate::application::hit::{self, Hit};
+use crate::application::job::{Job, JobState};
+use crate::application::processor::{self, Control};
+use crate::application::profile::Profile;
+use crate::application::status::Status;
+use crate::application::{actions, check, parse, queue, recovery, tag, transcriber, trash, Entry};
+use crate::store::Sort;
+use crate::{DATABASE, ROOT};
+
+#[derive(Parser, Debug)]
+#[command(name = "ccp", version, about = "Central Content Processor")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Full text search over all entries
+    Search {
+        /// Query, multiple words are joined. Filters like tag:cs2, lang:de,
+        /// date:2023-11..2023-12, duration:>30s, size:<100MB or id:>=10
+        /// narrow it down, a leading - excludes the matches
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Order by relevance, date, duration, size or id
+        #[arg(long)]
+        sort: Option<String>,
+        /// Start with the oldest, shortest, smallest or lowest id
+        #[arg(long)]
+        reverse: bool,
+    },
+    /// Create a new job for a file
+    Import {
+        /// Path to the file or name of a file in the ingest folder
+        file: String,
+        /// Start timestamps (HH:MM:SS), one per range
+        #[arg(long, num_args = 1.., required = true)]
+        start: Vec<String>,
+        /// End timestamps (HH:MM:SS), one per range
+        #[arg(long, num_args = 1.., required = true)]
+        end: Vec<String>,
+        #[arg(long)]
+        title: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        /// Language of the clip (ISO 639-1 code or name), detected if missing
+        #[arg(long)]
+        language: Option<String>,
+        /// Recording date (%d-%m-%Y), parsed from the filename if missing
+        #[arg(long)]
+        date: Option<String>,
+        /// One value per tag, spaces are kept
+        #[arg(long, num_args = 1..)]
+        tags: Vec<String>,
+        /// Encoding profile, selected from the streams if missing
+        #[arg(long)]
+        profile: Option<String>,
+        /// Re-encode the start instead of snapping to the previous keyframe
+        #[arg(long)]
+        accurate: bool,
+    },
+    /// List the encoding profiles
+    Profiles,
+    /// Execute all pending jobs, exits with an error if any of them failed
+    RunJobs {
+        /// Requeue failed and cancelled jobs first, resuming from their last checkpoint
+        #[arg(long)]
+        retry: bool,
+    },
+    /// Print a single entry
+    Show { id: i64 },
+    /// Copy an entry with its subtitles burned in to the export folder
+    Export {
+        id: i64,
+        /// Add the subtitles as a separate track instead
+        #[arg(long)]
+        soft: bool,
+    },
+    /// Move an entry with its files to the trash
+    Delete { id: i64 },
+    /// Move an entry back from the trash
+    Restore { id: i64 },
+    /// List the trashed entries
+    Trash {
+        /// Remove entries older than the retention period
+        #[arg(long)]
+        purge: bool,
+    },
+    /// Recreate the search index from the stored entries
+    Rebuild,
+    /// Compare the entries with the search index and the files
+    Check {
+        /// Apply the fix of every issue found, except for orphaned files
+        #[arg(long)]
+        fix: bool,
+        /// Move the files belonging to no entry to trash/orphans
+        #[arg(long)]
+        fix_orphans: bool,
+    },
+    /// Recreate the database and the index from the meta files, the old
+    /// database is kept in the store folder. Settings, profiles and jobs are
+    /// copied from it if it can still be read, otherwise they are lost and
+    /// the settings are reset to the defaults
+    Recover {
+        /// Folder the old database was moved to, set before the store is opened
+        #[arg(skip)]
+        backup: Option<String>,
+    },
+    /// Print the configuration or change a setting
+    Config {
+        /// Setting to change (transcriber, model, play_command, open_command,
+        /// folder_command, retention, title_boost, description_boost,
+        /// transcript_boost, tags_boost), `player` selects a play command preset
+        key: Option<String>,
+        value: Option<String>,
+    },
+}
+
+pub fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Search {
+            query,
+            sort,
+            reverse,
+        } => search(&query.join(" "), sort, reverse),
+        Command::Import {
+            file,
+            start,
+            end,
+            title,
+            description,
+            language,
+            date,
+            tags,
+            profile,
+            accurate,
+        } => import(
+            file,
+            start,
+            end,
+            title,
+            description,
+            language,
+            date,
+            tags,
+            profile,
+            accurate,
+        ),
+        Command::Profiles => profiles(),
+        Command::RunJobs { retry } => run_jobs(retry),
+        Command::Show { id } => show(id),
+        Command::Export { id, soft } => export(id, soft),
+        Command::Delete { id } => {
+            let entry: Entry = DATABASE.get_one(doc! {"_id": id})?;
+            trash::delete(&entry)
+        }
+        Command::Restore { id } => {
+            println!("Restored {}", trash::restore(id)?.search_str());
+            Ok(())
+        }
+        Command::Trash { purge } => list_trash(purge),
+        Command::Rebuild => {
+            let (entries, broken) = Entry::all()?;
+            DATABASE.rebuild(&entries, &mut |i, total| {
+                if i % 100 == 0 || i == total {
+                    println!("Indexed {}/{}", i, total);
+                }
+            })?;
+            println!("Rebuilt the index with {} entries", entries.len());
+            if !broken.is_empty() {
+                println!("Skipped undecodable entries {:?}", broken);
+            }
+            Ok(())
+        }
+        Command::Check { fix, fix_orphans } => check(fix, fix_orphans),
+        Command::Recover { backup } => recover(backup),
+        Command::Config { key, value } => config(key, value),
+    }
+}
+
+fn search(query: &str, sort: Option<String>, reverse: bool) -> Result<()> {
+    let sort = match sort {
+        Some(s) => Sort::from_str(&s)?,
+        None => Sort::default(),
+    };
+    let mut offset = 0;
+    loop {
+        let (hits, count) = Hit::search(query, sort, reverse, offset)?;
+        for hit in &hits {
+            println!("{}", hit.search_str());
+        }
+        if count < hit::PAGE {
+            return Ok(());
+        }
+        offset += count;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn import(
+    file: String,
+    start: Vec<String>,
+    end: Vec<String>,
+    title: String,
+    description: String,
+    language: Option<String>,
+    date: Option<String>,
+    tags: Vec<String>,
+    profile: Option<String>,
+    accurate: bool,
+) -> Result<()> {
+    let file = resolve(file);
+    let date = date.unwrap_or_else(|| {
+        let name = Path::new(&file)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let parsed = parse(&name);
+        if parsed.is_empty() {
+            Local::now().date_naive().format("%d-%m-%Y").to_string()
+        } else {
+            parsed
+        }
+    });
+
+    // Checked before the job is stored
+    let profile = profile.map(|p| Profile::get(&p)).transpose()?;
+    let job = Job::new(
+        file,
+        &start,
+        &end,
+        &[title],
+        &[description],
+        &language.into_iter().collect::<Vec<String>>(),
+        &[date],
+        &tags.iter().map(|t| tag::quote(t)).collect::<Vec<String>>(),
+        profile,
+        accurate,
+    )?;
+
+    println!("Created job {} ({})", job.uid, job.profile()?.name());
+    Ok(())
+}
+
+fn profiles() -> Result<()> {
+    for p in Profile::all()? {
+        println!("{} - {}", p.name(), p.description());
+    }
+    Ok(())
+}
+
+fn run_jobs(retry: bool) -> Result<()> {
+    if retry {
+        let failed = DATABASE
+            .get_many::<Job>(doc! {"done": false})?
+            .filter_map(Result::ok)
+            .filter(|j| matches!(j.state(), JobState::Failed | JobState::Cancelled));
+        for mut job in failed {
+            job.retry()?;
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut failed = 0;
+    while let Some(job) = queue::pending(&seen) {
+        seen.insert(job.uid.clone());
+        println!("Executing: {}", job.to_string());
+        let mut last = None;
+        for status in processor::execute(job, Control::default()).iter() {
+            if let Status::Failed(_) = status {
+                failed += 1;
+            }
+            let perc = status.get_perc();
+            if last != Some(perc) {
+                println!("{:?}", status);
+                last = Some(perc);
+            }
+        }
+    }
+
+    // Non-zero exit code for scripts
+    if failed > 0 {
+        return Err(anyhow!("{} of {} jobs failed", failed, seen.len()));
+    }
+    Ok(())
+}
+
+fn show(id: i64) -> Result<()> {
+    let entry: Entry = DATABASE.get_one(doc! {"_id": id})?;
+    println!("{}", serde_json::to_string_pretty(&entry)?);
+    Ok(())
+}
+
+fn export(id: i64, soft: bool) -> Result<()> {
+    let entry: Entry = DATABASE.get_one(doc! {"_id": id})?;
+    let subtitles = if soft {
+        Subtitles::Soft
+    } else {
+        Subtitles::Burned
+    };
+    println!("Exported to {}", export::export(&entry, subtitles)?);
+    Ok(())
+}
+
+fn list_trash(purge: bool) -> Result<()> {
+    if purge {
+        let (purged, broken) = trash::purge(DATABASE.get_config()?.retention())?;
+        println!("Purged {} entries", purged);
+        for b in broken {
+            eprintln!("Skipped {}", b);
+        }
+    }
+    let (trashed, broken) = trash::list()?;
+    for t in trashed {
+        println!(
+            "{} (deleted {})",
+            t.entry.search_str(),
+            t.deleted.format("%d-%m-%Y %H:%M")
+        );
+    }
+    if !purge {
+        for b in broken {
+            eprintln!("Skipped {}", b);
+        }
+    }
+    Ok(())
+}
+
+fn check(fix: bool, fix_orphans: bool) -> Result<()> {
+    let issues = check::check()?;
+    if issues.is_empty() {
+        println!("No issues found");
+    }
+    for issue in issues {
+        let apply = if issue.is_orphan() { fix_orphans } else { fix };
+        if !apply {
+            let flag = if issue.is_orphan() {
+                "--fix-orphans"
+            } else {
+                "--fix"
+            };
+            println!("{}, fix: {} ({})", issue, issue.remedy(), flag);
+            continue;
+        }
+        match issue.fix() {
+            Ok(()) => println!("{}, fixed: {}", issue, issue.remedy()),
+            Err(e) => println!("{}, unable to {}: {}", issue, issue.remedy(), e),
+        }
+    }
+    Ok(())
+}
+
+fn recover(backup: Option<String>) -> Result<()> {
+    let recovered = recovery::recover(backup.as_deref(), &mut |i, total| {
+        if i % 100 == 0 || i == total {
+            println!("Indexed {}/{}", i, total);
+        }
+    })?;
+    for (path, e) in &recovered.skipped {
+        println!("Skipped {}: {}", path.display(), e);
+    }
+    for (what, kept) in &recovered.kept {
+        match kept {
+            Ok(_) if *what == "settings" => println!("Kept the settings of the old database"),
+            Ok(n) => println!("Kept {} {} of the old database", n, what),
+            Err(e) => println!("Lost the {} of the old database: {}", what, e),
+        }
+    }
+    if recovered
+        .kept
+        .iter()
+        .any(|(what, kept)| *what == "settings" && kept.is_err())
+    {
+        println!("The settings were reset to the defaults, change them with `ccp config`");
+    }
+    println!(
+        "Recovered {} entries, the next id is {}",
+        recovered.entries,
+        recovered.counter + 1
+    );
+    Ok(())
+}
+
+fn config(key: Option<String>, value: Option<String>) -> Result<()> {
+    let (key, value) = match (key, value) {
+        (Some(k), Some(v)) => (k, v),
+        (None, None) => {
+            let conf = DATABASE.get_config()?;
+            println!("transcriber: {}", conf.transcriber());
+            println!("model: {}", conf.model());
+            println!("play_command: {}", conf.play_command());
+            println!("open_command: {}", conf.open_command());
+            println!("folder_command: {}", conf.folder_command());
+            println!("retention: {}", conf.retention());
+            for (field, boost) in conf.boosts() {
+                println!("{}_boost: {}", field, boost);
+            }
+            return Ok(());
+        }
+        _ => return Err(anyhow!("expected a setting and its value")),
+    };
+
+    let conf = DATABASE.get_config()?;
+    match key.as_str() {
+        "transcriber" => {
+            let _ = transcriber::backend(&value, conf.model())?;
+        }
+        "model" => {}
+        "retention" => {
+            let days: i64 = value
+                .parse()
+                .map_err(|_| anyhow!("retention is a number of days"))?;
+            return DATABASE.set_config(doc! {"retention": days});
+        }
+        "title_boost" | "description_boost" | "transcript_boost" | "tags_boost" => {
+            let boost: f64 = value
+                .parse()
+                .ok()
+                .filter(|b: &f64| *b >= 0.0)
+                .ok_or(anyhow!("a boost is a number of at least 0"))?;
+            return DATABASE.set_config(doc! {key: boost});
+        }
+        "play_command" | "open_command" | "folder_command" => {}
+        "player" => {
+            let (_, command) = actions::PLAYERS
+                .iter()
+                .find(|(name, _)| *name == value)
+                .ok_or(anyhow!("unknown player {}", value))?;
+            return DATABASE.set_config(doc! {"play_command": command});
+        }
+        _ => return Err(anyhow!("unknown setting {}", key)),
+    }
+    DATABASE.set_config(doc! {key: value})
+}
+
+/// Accepts both existing paths and bare names from the ingest folder.
+fn resolve(file: String) -> String {
+    if Path::new(&file).exists() {
+        file
+    } else {
+        format!("{}/ingest/{}", ROOT.as_str(), file)
+    }
+}
@@ -0,0 +1,135 @@
+//! # Editor
+//!
+//! Popup in the search tab to change an existing entry.
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Rect},
+    prelude::{Frame, Layout},
+    style::{Color, Style},
+    text::Span,
+    widgets::{Block, BorderType, Borders, Clear},
+};
+
+use crate::application::{tag, Entry};
+use crate::interface::{Render, TextArea};
+use crate::util;
+
+const INPUTS: usize = 4;
+const TITLES: [&str; INPUTS] = [" Title ", " Date ", " Tags ", " Description "];
+
+#[derive(Debug, Clone)]
+pub struct Editor {
+    entry: Entry,
+    // Title, date, tags, description
+    inputs: [TextArea; INPUTS],
+    selected: usize,
+    // Why saving failed
+    error: Option<String>,
+}
+
+impl Editor {
+    pub fn new(entry: Entry) -> Self {
+        let tags = entry
+            .tags()
+            .iter()
+            .map(|t| tag::quote(&t.to_string()))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let values = [
+            vec![entry.title().to_string()],
+            vec![entry.date().format("%d-%m-%Y").to_string()],
+            vec![tags],
+            entry.description().lines().map(String::from).collect(),
+        ];
+        let inputs = values.map(|v| {
+            let mut input = TextArea::new(v);
+            input.set_cursor_line_style(Style::default());
+            input
+        });
+
+        Self {
+            entry,
+            inputs,
+            selected: 0,
+            error: None,
+        }
+    }
+
+    /// Writes the changes, the entry is returned for the list.
+    pub fn save(&mut self) -> Option<Entry> {
+        let [title, date, tags, description] = &self.inputs;
+        match self.entry.edit(
+            title.lines(),
+            description.lines(),
+            tags.lines(),
+            date.lines(),
+        ) {
+            Ok(()) => Some(self.entry.clone()),
+            Err(e) => {
+                self.error = Some(e.to_string());
+                None
+            }
+        }
+    }
+}
+
+impl Render for Editor {
+    fn render(&mut self, f: &mut Frame, area: Rect) {
+        let area = util::center(60, 60, area);
+        let mut block = Block::default()
+            .title(format!(
+                " Edit {} (<C-s> save, <ESC> cancel) ",
+                self.entry.id()
+            ))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick);
+        if let Some(e) = &self.error {
+            block = block.title(Span::styled(
+                format!(" {} ", e),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        let inner = block.inner(area);
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+
+        let layout = Layout::default()
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(inner);
+
+        for (i, input) in self.inputs.iter_mut().enumerate() {
+            let color = if i == self.selected {
+                Color::Green
+            } else {
+                Color::Gray
+            };
+            input.set_block(
+                Block::default()
+                    .title(TITLES[i])
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .style(Style::default().fg(color)),
+            );
+            f.render_widget(input.widget(), layout[i]);
+        }
+    }
+
+    fn input(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Tab => self.selected = (self.selected + 1) % INPUTS,
+            KeyCode::BackTab => self.selected = (INPUTS + self.selected - 1) % INPUTS,
+            // Only the description has more than one line
+            KeyCode::Enter if self.selected != INPUTS - 1 => {}
+            _ => {
+                self.error = None;
+                self.inputs[self.selected].input(key);
+            }
+        }
+    }
+}
@@ -1,12 +1,10 @@
 //! # Execute
 
-use std::sync::mpsc::Receiver;
-
-use crate::application::processor;
+use crate::application::job::{Job, JobState};
 use crate::application::status::Status;
 use crate::interface::list::ItemList;
-use crate::DATABASE;
-use crate::{application::job::Job, interface::Render};
+use crate::interface::Render;
+use crate::{DATABASE, QUEUE};
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use polodb_core::bson::doc;
 use ratatui::style::{Modifier, Stylize};
@@ -18,43 +16,25 @@ use ratatui::{
     Frame,
 };
 
-#[derive(Debug)]
+/// Only displays the state of the global job queue, which keeps running
+/// when the tab is left.
+#[derive(Debug, Clone)]
 pub struct Execute {
-    count: usize,
     current: Option<Job>,
-    receiver: Option<Receiver<Status>>,
+    // Jobs finished by the queue at the last list refresh
+    finished: usize,
     status: Status,
     list: ItemList<Job>,
 }
 
-impl Clone for Execute {
-    fn clone(&self) -> Self {
-        Self {
-            count: self.count + 1,
-            current: self.current.clone(),
-            receiver: None,
-            status: self.status.clone(),
-            list: self.list.clone(),
-        }
-    }
-}
-
 impl Default for Execute {
     fn default() -> Self {
         let mut list = ItemList::default();
-        let mut jobs: Vec<Job> = Vec::new();
-        let found = DATABASE.get_many(doc! {"done": false}).unwrap();
-        for job in found {
-            if let Ok(j) = job {
-                jobs.push(j);
-            }
-        }
-        list.set(jobs);
+        list.set(Self::pending());
         Self {
-            count: 0,
             list,
             current: None,
-            receiver: None,
+            finished: QUEUE.progress().finished,
             status: Default::default(),
         }
     }
@@ -62,29 +42,13 @@ impl Default for Execute {
 
 impl Render for Execute {
     fn render(&mut self, f: &mut Frame, area: Rect) {
-        // TODO: Does this work?
-        if let Some(recv) = &self.receiver {
-            if let Ok(s) = recv.try_recv() {
-                self.status = s;
-            }
-        }
+        let progress = QUEUE.progress();
+        self.current = progress.current;
+        self.status = progress.status;
 
-        match self.status {
-            Status::Complete(_) => {
-                self.current = None;
-                self.receiver = None;
-                let mut list = ItemList::default();
-                let mut jobs: Vec<Job> = Vec::new();
-                let found = DATABASE.get_many(doc! {"done": false}).unwrap();
-                for job in found {
-                    if let Ok(j) = job {
-                        jobs.push(j);
-                    }
-                }
-                list.set(jobs);
-                self.list = list;
-            }
-            _ => {}
+        if progress.finished != self.finished {
+            self.finished = progress.finished;
+            self.list.set(Self::pending());
         }
 
         let layout = Layout::default()
@@ -105,36 +69,62 @@ impl Render for Execute {
                 self.list.previous();
             }
             KeyCode::Enter => {
-                // Execute current job
-                self.current = self.list.get();
-                if let Some(job) = &self.current {
-                    self.receiver = Some(processor::execute(job.clone()));
+                // Run the selected job first, then drain all other pending jobs
+                QUEUE.start(self.list.get());
+            }
+            KeyCode::Char('r') => {
+                if let Some(mut job) = self.list.get() {
+                    let stopped = matches!(job.state(), JobState::Failed | JobState::Cancelled);
+                    if stopped && job.retry().is_ok() {
+                        self.list.set_one(job.clone());
+                        QUEUE.start(Some(job));
+                    }
                 }
             }
+            KeyCode::Char('x') => {
+                QUEUE.cancel();
+            }
             _ => {}
         }
     }
 }
 
 impl Execute {
+    fn pending() -> Vec<Job> {
+        match DATABASE.get_many(doc! {"done": false}) {
+            Ok(found) => found.filter_map(Result::ok).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
     fn render_bar(&mut self, f: &mut Frame, area: Rect) {
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Length(1), Constraint::Min(0)])
             .split(area);
 
-        let text = {
-            if let Some(j) = &self.current {
-                format!("Execution: {}", j.get_file())
+        let (text, style) = {
+            let selected = self.list.get().filter(|j| j.state() == JobState::Failed);
+            if let Status::Failed(e) = &self.status {
+                (format!("Failed: {}", e), Style::default().fg(Color::Red))
+            } else if let Status::Cancelled = &self.status {
+                (
+                    String::from("Cancelled"),
+                    Style::default().fg(Color::Yellow),
+                )
+            } else if let Some(j) = &self.current {
+                let text = format!("Execution: {} (<x> to cancel)", j.get_file());
+                (text, Style::default())
+            } else if let Some(j) = selected {
+                let e = j.error().unwrap_or_default();
+                let text = format!("Attempt {} failed, <r> to retry: {}", j.attempts(), e);
+                (text, Style::default().fg(Color::Red))
             } else {
-                String::from("Execution: ")
+                (String::from("Execution: "), Style::default())
             }
         };
 
-        f.render_widget(
-            Paragraph::new(text).style(Style::default().bold()),
-            layout[0],
-        );
+        f.render_widget(Paragraph::new(text).style(style.bold()), layout[0]);
         self.render_parts(f, layout[1]);
     }
 
@@ -203,7 +193,15 @@ impl Execute {
             .list
             .items()
             .iter()
-            .map(|x| ListItem::new(x.to_string()))
+            .map(|x| {
+                let running = self.current.as_ref().map(|c| c.uid == x.uid);
+                if running == Some(true) {
+                    ListItem::new(format!("> [{}] {}", x.state(), x.to_string()))
+                        .style(Style::default().bold())
+                } else {
+                    ListItem::new(format!("[{}] {}", x.state(), x.to_string()))
+                }
+            })
             .collect();
 
         let list = List::new(listed)
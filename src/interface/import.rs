@@ -2,7 +2,12 @@
 
 use std::{fs::File, time::Instant};
 
-use crate::application::{actions, job::Job, tag::Tag, Metadata};
+use crate::application::{
+    actions,
+    job::Job,
+    tag::{self, Tag},
+    Metadata,
+};
 use crate::{
     interface::{Render, TextArea},
     update::control,
@@ -14,7 +19,7 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout},
     prelude::Rect,
     style::{Color, Style},
-    widgets::{block::Title, Block, BorderType, Borders, List, ListItem, Paragraph},
+    widgets::{block::Title, Block, BorderType, Borders, List, ListItem, Paragraph, Wrap},
     Frame,
 };
 
@@ -43,6 +48,8 @@ pub struct Import {
     timestamp: TextArea,
     // Selector list on bottom
     tagslist: ItemList<Tag>,
+    // Frame accurate cut for the created job
+    accurate: bool,
     // System state
     meta: Meta,
 }
@@ -55,6 +62,8 @@ struct Meta {
     popped: bool,
     // When was submitted
     moment: Instant,
+    // Shown instead of the job result
+    error: Option<String>,
 }
 
 impl Default for Meta {
@@ -63,6 +72,7 @@ impl Default for Meta {
             valid: false,
             popped: false,
             moment: Instant::now(),
+            error: None,
         }
     }
 }
@@ -75,7 +85,9 @@ impl Render for Import {
             if self.meta.moment.elapsed().as_secs_f64() > 1.25 {
                 self.meta.popped = false;
             } else {
-                let (text, style) = if self.meta.valid {
+                let (text, style) = if let Some(e) = &self.meta.error {
+                    (e.clone(), Style::default().fg(Color::Red))
+                } else if self.meta.valid {
                     (format!("Job created!"), Style::default().fg(Color::Green))
                 } else {
                     (format!("Invalid inputs!"), Style::default().fg(Color::Red))
@@ -86,7 +98,13 @@ impl Render for Import {
                     .border_type(BorderType::Thick)
                     .style(style.clone());
                 let center = util::center(10, 15, area);
-                f.render_widget(Paragraph::new(text).block(block).style(style), center);
+                f.render_widget(
+                    Paragraph::new(text)
+                        .block(block)
+                        .style(style)
+                        .wrap(Wrap { trim: true }),
+                    center,
+                );
                 return;
             }
         }
@@ -169,6 +187,9 @@ impl Render for Import {
             KeyCode::Char('s') if key.modifiers == KeyModifiers::CONTROL => {
                 self.save_job();
             }
+            KeyCode::Char('a') if key.modifiers == KeyModifiers::CONTROL => {
+                self.accurate = !self.accurate;
+            }
             KeyCode::Char('j') if key.modifiers == KeyModifiers::CONTROL => {
                 self.tagslist.next();
             }
@@ -177,13 +198,17 @@ impl Render for Import {
             }
             KeyCode::Char('o') if key.modifiers == KeyModifiers::CONTROL => {
                 if let Some(path) = self.get_path() {
-                    let _ = actions::open_file(&path);
+                    if let Err(e) = actions::open_file(&path) {
+                        self.meta.popped = true;
+                        self.meta.moment = Instant::now();
+                        self.meta.error = Some(e.to_string());
+                    }
                 }
             }
             KeyCode::Enter => {
                 // Submit selected tag
                 if let Some(tag) = self.tagslist.get() {
-                    let _ = self.tags.insert_str(tag.to_string() + " ");
+                    let _ = self.tags.insert_str(tag::quote(&tag.to_string()) + " ");
                     self.selected = 6;
                 }
             }
@@ -205,6 +230,7 @@ impl Render for Import {
 
 impl Import {
     fn save_job(&mut self) {
+        self.meta.error = None;
         self.meta.popped = true;
         self.meta.moment = Instant::now();
         if let Some(file) = self.get_path() {
@@ -217,6 +243,8 @@ impl Import {
                 self.language.lines(),
                 self.timestamp.lines(),
                 self.tags.lines(),
+                None,
+                self.accurate,
             ) {
                 self.meta.valid = true;
             } else {
@@ -374,8 +402,13 @@ impl Import {
             .border_type(BorderType::Rounded);
 
         // TODO: Get title
+        let heading = if self.accurate {
+            " File (accurate cut, <C-a>) "
+        } else {
+            " File (keyframe cut, <C-a>) "
+        };
         let title = Paragraph::new(format!(" {}", self.get_title()))
-            .block(block.clone().title(" File "))
+            .block(block.clone().title(heading))
             .style(Style::default().fg(Color::Yellow));
 
         f.render_widget(title, layout[0]);
@@ -473,11 +506,11 @@ impl Default for Import {
         timestamp.set_block(block.clone());
         timestamp.set_cursor_line_style(style.clone());
         let mut start = TextArea::default();
-        start.set_placeholder_text(" Start HH:MM:SS");
+        start.set_placeholder_text(" Start HH:MM:SS, ...");
         start.set_block(block.clone().title(" Start Time "));
         start.set_cursor_line_style(style.clone());
         let mut end = TextArea::default();
-        end.set_placeholder_text(" End HH:MM:SS");
+        end.set_placeholder_text(" End HH:MM:SS, ...");
         end.set_block(block.clone().title(" End Time "));
         end.set_cursor_line_style(style.clone());
         let mut title = TextArea::default();
@@ -489,7 +522,7 @@ impl Default for Import {
         description.set_block(block.clone().title(" Description "));
         description.set_cursor_line_style(style.clone());
         let mut language = TextArea::default();
-        language.set_placeholder_text(" de, en");
+        language.set_placeholder_text(" de, en, ... (auto)");
         language.set_block(block.clone().title(" Language "));
         language.set_cursor_line_style(style.clone());
         let mut tags = TextArea::default();
@@ -518,6 +551,7 @@ impl Default for Import {
             meta: Meta::default(),
             timestamp,
             tagslist: ItemList::new(alltags),
+            accurate: false,
         }
     }
 }
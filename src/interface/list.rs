@@ -43,6 +43,10 @@ impl<E: Debug + Clone> ItemList<E> {
         self.items = items;
     }
 
+    pub fn extend(&mut self, items: Vec<E>) {
+        self.items.extend(items);
+    }
+
     pub fn set_one(&mut self, item: E) {
         if let Some(i) = self.state.selected() {
             if let Some(elem) = self.items.get_mut(i) {
@@ -34,6 +34,8 @@ use footer::Footer;
 
 mod list;
 
+mod editor;
+
 pub type TextArea = TuiTextArea<'static>;
 
 pub const STATES: i32 = 5;
@@ -70,6 +72,9 @@ impl Interface {
 
     pub fn tick(&mut self) {
         self.header.tick();
+        if let State::Search(s) = &mut self.state {
+            s.tick();
+        }
     }
 }
 
@@ -2,26 +2,63 @@
 
 use crate::interface::{Render, TextArea};
 use crate::update::control;
-use crate::DATABASE;
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crossterm::event::{KeyCode, KeyEvent};
-use ratatui::layout::{Constraint, Direction, Layout};
-use ratatui::style::{Color, Style};
-use ratatui::widgets::{Block, BorderType, Borders, Paragraph};
+use ratatui::layout::{Alignment, Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{block::Title, Block, BorderType, Borders, Paragraph};
 use ratatui::{
     layout::Rect,
     prelude::Frame,
     widgets::{List, ListItem},
 };
 
+use super::editor::Editor;
 use super::list::ItemList;
-use crate::application::{actions, Entry};
+use crate::application::export::{self, Subtitles};
+use crate::application::{
+    actions,
+    hit::{self, Hit},
+    trash,
+};
+use crate::store::{Snippet, Sort};
+
+// Typing pause before the query runs
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+// Results of a background search with its generation
+type Found = Option<(usize, Result<(Vec<Hit>, usize), String>)>;
 
 #[derive(Debug, Clone)]
 pub struct Search {
     input: TextArea,
     text: String,
-    list: ItemList<Entry>,
+    list: ItemList<Hit>,
+    sort: Sort,
+    reverse: bool,
+    // Last page was full, there might be more hits
+    more: bool,
+    // Hits loaded from the index, including unreadable ones
+    fetched: usize,
+    // Time of the last edit, the search starts on the first tick after the debounce
+    pending: Option<Instant>,
+    // Incremented for every search, older results are discarded
+    generation: usize,
+    searching: bool,
+    found: Arc<Mutex<Found>>,
+    // Error of the last action
+    error: Option<String>,
+    // Progress of the running export, set from its thread
+    status: Arc<Mutex<Option<String>>>,
+    // Open while the selected entry is edited
+    editor: Option<Editor>,
+    // Entry to delete on the next <C-d>
+    delete: Option<i64>,
 }
 
 impl Default for Search {
@@ -35,16 +72,25 @@ impl Default for Search {
                 .border_type(BorderType::Rounded),
         );
 
-        let mut items = Vec::new();
-        if let Ok(dflt) = DATABASE.search("*") {
-            items = dflt;
-        }
-
-        Self {
+        let mut search = Self {
             input,
             text: String::new(),
-            list: ItemList::new(items),
-        }
+            list: ItemList::default(),
+            sort: Sort::default(),
+            reverse: false,
+            more: false,
+            fetched: 0,
+            pending: None,
+            generation: 0,
+            searching: false,
+            found: Arc::new(Mutex::new(None)),
+            error: None,
+            status: Arc::new(Mutex::new(None)),
+            editor: None,
+            delete: None,
+        };
+        search.search();
+        search
     }
 }
 
@@ -56,12 +102,36 @@ impl Render for Search {
 
         self.render_bar(f, layout[0]);
         self.render_list(f, layout[1]);
+
+        if let Some(editor) = &mut self.editor {
+            editor.render(f, area);
+        }
     }
 
     fn input(&mut self, key: KeyEvent) {
+        if let Some(editor) = &mut self.editor {
+            match key.code {
+                KeyCode::Esc => self.editor = None,
+                KeyCode::Char('s') if control(&key) => {
+                    if let Some(entry) = editor.save() {
+                        if let Some(mut hit) = self.list.get() {
+                            hit.entry = entry;
+                            self.list.set_one(hit);
+                        }
+                        self.editor = None;
+                    }
+                }
+                _ => editor.input(key),
+            }
+            return;
+        }
+        let delete = self.delete.take();
+        if delete.is_some() && !(key.code == KeyCode::Char('d') && control(&key)) {
+            *self.status.lock().unwrap() = None;
+        }
         match key.code {
             KeyCode::Char('j') if control(&key) => {
-                self.list.next();
+                self.next();
             }
             KeyCode::Char('k') if control(&key) => {
                 self.list.previous();
@@ -70,40 +140,67 @@ impl Render for Search {
                 self.list.previous();
             }
             KeyCode::Down => {
-                self.list.next();
+                self.next();
+            }
+            KeyCode::Char('t') if control(&key) => {
+                self.sort = self.sort.next();
+                self.search();
+            }
+            KeyCode::Char('r') if control(&key) => {
+                self.reverse = !self.reverse;
+                self.search();
             }
             KeyCode::Char('o') if control(&key) => {
-                if let Some(e) = self.list.get() {
-                    self.text = e.out_path().unwrap();
-                    let _ = actions::open_nautilus(e);
+                if let Some(h) = self.list.get() {
+                    self.error = actions::open_folder(h.entry).err().map(|e| e.to_string());
                 }
             }
+            KeyCode::Char('u') if control(&key) => {
+                if let Some(h) = self.list.get() {
+                    self.editor = Some(Editor::new(h.entry));
+                }
+            }
+            KeyCode::Char('d') if control(&key) => {
+                if let Some(h) = self.list.get() {
+                    if delete != Some(h.entry.id()) {
+                        *self.status.lock().unwrap() = Some(format!(
+                            "Move {} to the trash? <C-d> to confirm",
+                            h.entry.id()
+                        ));
+                        self.delete = Some(h.entry.id());
+                        self.error = None;
+                        return;
+                    }
+                    self.error = match trash::delete(&h.entry) {
+                        Ok(()) => {
+                            self.remove_selected();
+                            *self.status.lock().unwrap() =
+                                Some(format!("Moved {} to the trash", h.entry.id()));
+                            None
+                        }
+                        Err(e) => Some(e.to_string()),
+                    };
+                }
+            }
+            KeyCode::Char('e') if control(&key) => {
+                self.export(Subtitles::Burned);
+            }
+            KeyCode::Char('s') if control(&key) => {
+                self.export(Subtitles::Soft);
+            }
             KeyCode::Enter => {
                 // TODO Open event
-                if let Some(e) = self.list.get() {
-                    self.text = e.out_path().unwrap();
-                    let _ = actions::open_vlc(e);
+                if let Some(h) = self.list.get() {
+                    let start = h.segments.first().map(|s| s.start);
+                    self.error = actions::play(h.entry, start).err().map(|e| e.to_string());
                 }
             }
             _ => {
                 self.input.input(key);
-                // not nice workaroud
-                for l in self.input.lines() {
-                    self.text = l.to_string();
-                    break;
-                }
-                if self.text.len() >= 1 {
-                    match DATABASE.search(&self.text) {
-                        Ok(elems) => {
-                            self.list.set(elems);
-                            self.list.select(None);
-                        }
-                        Err(_) => {
-                            // TODO: Maybe reset list to empty
-                            self.list.set(Vec::new());
-                            self.list.select(None);
-                        }
-                    }
+                let text = self.input.lines().first().cloned().unwrap_or_default();
+                if text != self.text {
+                    self.text = text;
+                    self.pending = Some(Instant::now());
                 }
             }
         }
@@ -111,6 +208,120 @@ impl Render for Search {
 }
 
 impl Search {
+    // Everything if the input is empty
+    fn query(&self) -> &str {
+        match self.text.trim() {
+            "" => "*",
+            t => t,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if self.pending.is_some_and(|t| t.elapsed() >= DEBOUNCE) {
+            self.search();
+        }
+        let found = self.found.lock().unwrap().take();
+        match found {
+            Some((generation, result)) if generation == self.generation => {
+                self.searching = false;
+                self.show(result);
+            }
+            // Stale
+            _ => {}
+        }
+    }
+
+    // Fetches the first page for the current input in the background
+    fn search(&mut self) {
+        self.pending = None;
+        self.generation += 1;
+        self.searching = true;
+        let generation = self.generation;
+        let query = self.query().to_string();
+        let (sort, reverse) = (self.sort, self.reverse);
+        let found = self.found.clone();
+        thread::spawn(move || {
+            let result = Hit::search(&query, sort, reverse, 0).map_err(|e| e.to_string());
+            let mut found = found.lock().unwrap();
+            // A newer search might have finished first
+            let newer = matches!(&*found, Some((g, _)) if *g > generation);
+            if !newer {
+                *found = Some((generation, result));
+            }
+        });
+    }
+
+    // Replaces the hits, the selected entry stays selected if it is still found
+    fn show(&mut self, result: Result<(Vec<Hit>, usize), String>) {
+        let (hits, count) = match result {
+            Ok(found) => found,
+            // Keeps the last results while the query is incomplete
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
+        self.error = None;
+        self.more = count == hit::PAGE;
+        self.fetched = count;
+        let selected = self.list.get().map(|h| {
+            hits.iter()
+                .position(|n| n.entry.id() == h.entry.id())
+                .unwrap_or_default()
+        });
+        let empty = hits.is_empty();
+        self.list.set(hits);
+        self.list.select(selected.filter(|_| !empty));
+    }
+
+    // Loads the next page before moving past the last hit, unless the
+    // hits are about to be replaced
+    fn next(&mut self) {
+        let last = self.list.items().len().checked_sub(1);
+        let settled = self.pending.is_none() && !self.searching;
+        if self.more && settled && self.list.state.selected() == last {
+            match Hit::search(self.query(), self.sort, self.reverse, self.fetched) {
+                Ok((hits, count)) => {
+                    self.more = count == hit::PAGE;
+                    self.fetched += count;
+                    self.list.extend(hits);
+                }
+                Err(e) => self.error = Some(e.to_string()),
+            }
+        }
+        self.list.next();
+    }
+
+    // After deleting the selected hit, which also left the index
+    fn remove_selected(&mut self) {
+        let mut items = self.list.items().clone();
+        if let Some(i) = self.list.state.selected() {
+            items.remove(i);
+            self.fetched = self.fetched.saturating_sub(1);
+            if i >= items.len() {
+                self.list.select(items.len().checked_sub(1));
+            }
+        }
+        self.list.set(items);
+    }
+
+    fn export(&mut self, subtitles: Subtitles) {
+        let entry = match self.list.get() {
+            Some(h) => h.entry,
+            None => return,
+        };
+        self.error = None;
+        let status = self.status.clone();
+        *status.lock().unwrap() = Some(format!("Exporting {}...", entry.id()));
+        thread::spawn(move || {
+            let message = match export::export(&entry, subtitles) {
+                Ok(path) => format!("Exported to {}", path),
+                Err(e) => e.to_string(),
+            };
+            *status.lock().unwrap() = Some(message);
+        });
+    }
+
     fn render_bar(&mut self, f: &mut Frame, area: Rect) {
         let layout = Layout::default()
             .constraints([Constraint::Percentage(5), Constraint::Min(1)])
@@ -134,17 +345,64 @@ impl Search {
             .list
             .items()
             .iter()
-            .map(|x| ListItem::new(x.search_str()).style(Style::default()))
+            .map(|x| {
+                let mut lines = vec![Line::from(x.search_str())];
+                for snippet in [&x.description, &x.transcript] {
+                    if !snippet.highlights.is_empty() {
+                        lines.push(highlighted(snippet));
+                    }
+                }
+                ListItem::new(lines).style(Style::default())
+            })
             .collect();
 
+        let sort = match (self.sort, self.reverse) {
+            (Sort::Relevance, _) => String::from(" Sort: relevance (<C-t>) "),
+            (s, false) => format!(" Sort: {}, descending (<C-t>, <C-r>) ", s.name()),
+            (s, true) => format!(" Sort: {}, ascending (<C-t>, <C-r>) ", s.name()),
+        };
+        let mut block = Block::default()
+            .title(Title::from(sort).alignment(Alignment::Right))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        if let Some(e) = &self.error {
+            block = block.title(Span::styled(
+                format!(" {} ", e),
+                Style::default().fg(Color::Red),
+            ));
+        } else if let Some(s) = self.status.lock().unwrap().as_ref() {
+            block = block.title(Span::styled(
+                format!(" {} ", s),
+                Style::default().fg(Color::Gray),
+            ));
+        }
+
         let list = List::new(listed)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_type(BorderType::Rounded),
-            )
+            .block(block)
             .highlight_style(Style::default().fg(Color::Gray));
 
         f.render_stateful_widget(list, area, &mut self.list.state);
     }
 }
+
+// Indented snippet with the matched terms in bold yellow
+fn highlighted(snippet: &Snippet) -> Line<'static> {
+    let text = snippet.text.replace('\n', " ");
+    let mut spans = vec![Span::raw("    ")];
+    let mut at = 0;
+    for range in &snippet.highlights {
+        if range.start < at || range.end > text.len() {
+            continue;
+        }
+        spans.push(Span::raw(text[at..range.start].to_string()));
+        spans.push(Span::styled(
+            text[range.clone()].to_string(),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+        at = range.end;
+    }
+    spans.push(Span::raw(text[at..].to_string()));
+    Line::from(spans)
+}
@@ -1,27 +1,323 @@
 //! # Settings
 
-use ratatui::widgets::{block::Title, Block, BorderType, Borders, Paragraph};
+use std::sync::{Arc, Mutex};
+use std::thread;
 
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Layout, Rect},
+    prelude::Frame,
+    style::{Color, Style},
+    widgets::{block::Title, Block, BorderType, Borders, List, ListItem, Paragraph, Wrap},
+};
+
+use super::list::ItemList;
+use crate::application::check::{self, Issue};
+use crate::application::trash::{self, Trashed};
+use crate::application::Entry;
 use crate::interface::Render;
+use crate::update::control;
+use crate::DATABASE;
+
+// TODO: Refetch aggregates
+const ACTIONS: [&str; 5] = [
+    "Rebuild search index",
+    "Check for entropy issues",
+    "Fix the issues found",
+    "Move the orphaned files found",
+    "Restore from the trash",
+];
+
+// Actions asking for a second <RETURN> first
+const CONFIRM: [&str; 1] = ["Move the orphaned files found"];
+
+#[derive(Clone, Debug)]
+pub struct Settings {
+    actions: ItemList<&'static str>,
+    // Output of the running or last action, set from its thread
+    status: Arc<Mutex<String>>,
+    running: Arc<Mutex<bool>>,
+    // Found by the last check
+    issues: Arc<Mutex<Vec<Issue>>>,
+    // Action waiting for confirmation
+    confirm: Option<&'static str>,
+    // Open while picking an entry to restore
+    trash: Option<ItemList<Trashed>>,
+}
 
-#[derive(Clone, Debug, Default)]
-pub struct Settings {}
+impl Default for Settings {
+    fn default() -> Self {
+        let mut actions = ItemList::new(ACTIONS.to_vec());
+        actions.select(Some(0));
+        Self {
+            actions,
+            status: Arc::new(Mutex::new(String::new())),
+            running: Arc::new(Mutex::new(false)),
+            issues: Arc::new(Mutex::new(Vec::new())),
+            confirm: None,
+            trash: None,
+        }
+    }
+}
 
 impl Render for Settings {
-    fn render(&mut self, f: &mut ratatui::prelude::Frame, area: ratatui::prelude::Rect) {
-        let text = format!(
-            "Clear FTS Cache \nCheck for entropy issues. \nTODO: Add buttons \nRefetch aggregates"
-        );
+    fn render(&mut self, f: &mut Frame, area: Rect) {
+        let layout = Layout::default()
+            .constraints([
+                Constraint::Length(ACTIONS.len() as u16 + 2),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        let items: Vec<ListItem> = self
+            .actions
+            .items()
+            .iter()
+            .map(|a| ListItem::new(format!(" {}", a)))
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(Title::from(" Settings (<RETURN> to run) "))
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .highlight_style(Style::default().fg(Color::Cyan));
+        f.render_stateful_widget(list, layout[0], &mut self.actions.state);
+
+        let status = self.status.lock().unwrap().clone();
+        let output = match &mut self.trash {
+            Some(trashed) => {
+                let parts = Layout::default()
+                    .constraints([Constraint::Min(0), Constraint::Length(6)])
+                    .split(layout[1]);
+                render_trash(f, parts[0], trashed);
+                parts[1]
+            }
+            None => layout[1],
+        };
         f.render_widget(
-            Paragraph::new(text).block(
+            Paragraph::new(status).wrap(Wrap { trim: false }).block(
                 Block::default()
-                    .title(Title::from(" Settings "))
+                    .title(Title::from(" Output "))
                     .borders(Borders::ALL)
                     .border_type(BorderType::Rounded),
             ),
-            area,
+            output,
+        )
+    }
+
+    fn input(&mut self, key: KeyEvent) {
+        if let Some(trashed) = &mut self.trash {
+            match key.code {
+                KeyCode::Char('j') if control(&key) => trashed.next(),
+                KeyCode::Char('k') if control(&key) => trashed.previous(),
+                KeyCode::Down => trashed.next(),
+                KeyCode::Up => trashed.previous(),
+                KeyCode::Enter => self.restore(),
+                KeyCode::Esc => self.trash = None,
+                _ => {}
+            }
+            return;
+        }
+        if key.code != KeyCode::Enter && self.confirm.take().is_some() {
+            *self.status.lock().unwrap() = String::from("Cancelled");
+        }
+        match key.code {
+            KeyCode::Char('j') if control(&key) => self.actions.next(),
+            KeyCode::Char('k') if control(&key) => self.actions.previous(),
+            KeyCode::Down => self.actions.next(),
+            KeyCode::Up => self.actions.previous(),
+            KeyCode::Enter => self.run(),
+            _ => {}
+        }
+    }
+}
+
+impl Settings {
+    // Runs the selected action in the background, one at a time
+    fn run(&mut self) {
+        let action = match self.actions.get() {
+            Some(a) => a,
+            None => return,
+        };
+        {
+            let mut running = self.running.lock().unwrap();
+            if *running {
+                return;
+            }
+            if CONFIRM.contains(&action) && self.confirm.take() != Some(action) {
+                let orphans = self
+                    .issues
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|i| i.is_orphan())
+                    .count();
+                *self.status.lock().unwrap() = format!(
+                    "Move {} orphaned files to trash/orphans? They might belong to entries the check \
+                     couldn't read.\n<RETURN> to confirm, any other key to cancel",
+                    orphans
+                );
+                self.confirm = Some(action);
+                return;
+            }
+            // Picked in the foreground, not while a rebuild might run
+            if action == "Restore from the trash" {
+                drop(running);
+                self.open_trash();
+                return;
+            }
+            *running = true;
+        }
+
+        let status = self.status.clone();
+        let running = self.running.clone();
+        let issues = self.issues.clone();
+        thread::spawn(move || {
+            let result = match action {
+                "Rebuild search index" => rebuild(&status),
+                "Check for entropy issues" => find_issues(&status, &issues),
+                "Fix the issues found" => fix_issues(&status, &issues),
+                "Move the orphaned files found" => move_orphans(&status, &issues),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                *status.lock().unwrap() = format!("{} failed: {}", action, e);
+            }
+            *running.lock().unwrap() = false;
+        });
+    }
+}
+
+impl Settings {
+    fn open_trash(&mut self) {
+        let (trashed, broken) = match trash::list() {
+            Ok(found) => found,
+            Err(e) => {
+                *self.status.lock().unwrap() = format!("Unable to list the trash: {}", e);
+                return;
+            }
+        };
+        let mut lines = vec![format!("{} entries in the trash", trashed.len())];
+        lines.extend(broken.iter().map(|b| format!("Skipped {}", b)));
+        *self.status.lock().unwrap() = lines.join("\n");
+        let mut list = ItemList::new(trashed);
+        list.select(Some(0));
+        self.trash = Some(list);
+    }
+
+    fn restore(&mut self) {
+        let trashed = match &mut self.trash {
+            Some(t) => t,
+            None => return,
+        };
+        let selected = match trashed.get() {
+            Some(t) => t,
+            None => return,
+        };
+        *self.status.lock().unwrap() = match trash::restore(selected.entry.id()) {
+            Ok(entry) => {
+                let mut items = trashed.items().clone();
+                items.retain(|t| t.entry.id() != entry.id());
+                let last = items.len().checked_sub(1);
+                trashed.set(items);
+                if trashed.state.selected() > last {
+                    trashed.select(last);
+                }
+                format!("Restored {}", entry.search_str())
+            }
+            Err(e) => format!("Unable to restore {}: {}", selected.entry.id(), e),
+        };
+    }
+}
+
+fn render_trash(f: &mut Frame, area: Rect, trashed: &mut ItemList<Trashed>) {
+    let items: Vec<ListItem> = trashed
+        .items()
+        .iter()
+        .map(|t| {
+            ListItem::new(format!(
+                " {} (deleted {})",
+                t.entry.search_str(),
+                t.deleted.format("%d-%m-%Y %H:%M")
+            ))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(Title::from(" Trash (<RETURN> to restore, <ESC> to close) "))
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
         )
+        .highlight_style(Style::default().fg(Color::Cyan));
+    f.render_stateful_widget(list, area, &mut trashed.state);
+}
+
+fn rebuild(status: &Mutex<String>) -> anyhow::Result<()> {
+    *status.lock().unwrap() = String::from("Loading entries...");
+    let (entries, broken) = Entry::all()?;
+    DATABASE.rebuild(&entries, &mut |i, total| {
+        *status.lock().unwrap() = format!("Indexing {}/{} entries...", i, total);
+    })?;
+    let mut done = format!("Rebuilt the search index with {} entries", entries.len());
+    if !broken.is_empty() {
+        done += &format!("\nSkipped undecodable entries {:?}", broken);
     }
+    *status.lock().unwrap() = done;
+    Ok(())
+}
+
+fn find_issues(status: &Mutex<String>, issues: &Mutex<Vec<Issue>>) -> anyhow::Result<()> {
+    *status.lock().unwrap() = String::from("Checking...");
+    let found = check::check()?;
+    let mut lines = vec![format!("{} issues found", found.len())];
+    lines.extend(found.iter().map(|i| format!("{}, fix: {}", i, i.remedy())));
+    *status.lock().unwrap() = lines.join("\n");
+    *issues.lock().unwrap() = found;
+    Ok(())
+}
 
-    fn input(&mut self, _key: crossterm::event::KeyEvent) {}
+// Orphans are kept for `move_orphans`
+fn fix_issues(status: &Mutex<String>, issues: &Mutex<Vec<Issue>>) -> anyhow::Result<()> {
+    let found = std::mem::take(&mut *issues.lock().unwrap());
+    let (orphans, found): (Vec<Issue>, Vec<Issue>) = found.into_iter().partition(|i| i.is_orphan());
+    let (fixed, errors) = fix_all(found);
+    let mut lines = vec![format!("Fixed {} issues", fixed)];
+    if !orphans.is_empty() {
+        lines.push(format!(
+            "Kept {} orphaned files, move them with \"{}\"",
+            orphans.len(),
+            ACTIONS[3]
+        ));
+    }
+    lines.extend(errors);
+    *issues.lock().unwrap() = orphans;
+    *status.lock().unwrap() = lines.join("\n");
+    Ok(())
+}
+
+fn move_orphans(status: &Mutex<String>, issues: &Mutex<Vec<Issue>>) -> anyhow::Result<()> {
+    let found = std::mem::take(&mut *issues.lock().unwrap());
+    let (orphans, rest): (Vec<Issue>, Vec<Issue>) = found.into_iter().partition(|i| i.is_orphan());
+    let (moved, errors) = fix_all(orphans);
+    let mut lines = vec![format!("Moved {} orphaned files", moved)];
+    lines.extend(errors);
+    *issues.lock().unwrap() = rest;
+    *status.lock().unwrap() = lines.join("\n");
+    Ok(())
+}
+
+// Number of fixed issues and a line for each failed one
+fn fix_all(issues: Vec<Issue>) -> (usize, Vec<String>) {
+    let mut fixed = 0;
+    let mut errors = Vec::new();
+    for issue in issues {
+        match issue.fix() {
+            Ok(()) => fixed += 1,
+            Err(e) => errors.push(format!("{}, unable to {}: {}", issue, issue.remedy(), e)),
+        }
+    }
+    (fixed, errors)
 }
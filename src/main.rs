@@ -4,6 +4,7 @@ use anyhow::Result;
 use store::Database;
 
 mod application;
+mod cli;
 mod handler;
 mod interface;
 mod store;
@@ -13,7 +14,9 @@ mod util;
 
 use crate::handler::Event;
 
+use application::queue::Queue;
 use application::App;
+use clap::Parser;
 // use crossterm::event::{self, KeyCode, KeyEventKind};
 use lazy_static::lazy_static;
 use polodb_core::bson::doc;
@@ -23,10 +26,28 @@ use std::io::stderr;
 lazy_static! {
     pub static ref ROOT: String = std::env::var("CCP_ROOT").unwrap_or("/temp".to_string());
     pub static ref DATABASE: Database = Database::new().unwrap();
+    pub static ref QUEUE: Queue = Queue::default();
 }
 
 fn main() -> Result<()> {
+    let mut args = cli::Cli::parse();
+    // Before the store is opened
+    if let Some(cli::Command::Recover { backup }) = &mut args.command {
+        *backup = application::recovery::set_aside()?;
+        if let Some(backup) = backup {
+            println!("Moved the old database to {}", backup);
+        }
+    }
+
     util::ensure_configured()?;
+    // The fresh database of a recovery only has the default retention
+    if !matches!(args.command, Some(cli::Command::Recover { .. })) {
+        util::purge_trash()?;
+    }
+
+    if let Some(command) = args.command {
+        return cli::run(command);
+    }
 
     let mut app = App::default();
     // let _ = manual();
@@ -108,6 +129,8 @@ fn main() -> Result<()> {
         }
     }
     tui.exit()?;
+    // Don't leave ffmpeg or whisper running
+    QUEUE.shutdown();
 
     Ok(())
 }
@@ -10,12 +10,107 @@ pub struct Config {
     #[serde(rename = "_id")]
     id: String,
     counter: i64,
+    // Name of the transcription backend
+    #[serde(default = "default_transcriber")]
+    transcriber: String,
+    // Whisper model name, or path to a ggml file for whisper.cpp
+    #[serde(default = "default_model")]
+    model: String,
+    // Command templates of the external programs, see `actions`
+    #[serde(default = "default_play")]
+    play_command: String,
+    #[serde(default = "default_open")]
+    open_command: String,
+    #[serde(default = "default_folder")]
+    folder_command: String,
+    // Days deleted entries are kept in the trash
+    #[serde(default = "default_retention")]
+    retention: i64,
+    // Weights of the fields searched without a field name
+    #[serde(default = "default_title_boost")]
+    title_boost: f64,
+    #[serde(default = "default_boost")]
+    description_boost: f64,
+    #[serde(default = "default_boost")]
+    transcript_boost: f64,
+    #[serde(default = "default_tags_boost")]
+    tags_boost: f64,
+}
+
+fn default_transcriber() -> String {
+    String::from("whisper")
+}
+
+fn default_model() -> String {
+    String::from("medium")
+}
+
+fn default_play() -> String {
+    String::from("vlc --start-time={start} --sub-file={subs} {path}")
+}
+
+fn default_open() -> String {
+    String::from("vlc {path}")
+}
+
+fn default_folder() -> String {
+    String::from("nautilus --select {path}")
+}
+
+fn default_retention() -> i64 {
+    30
+}
+
+fn default_title_boost() -> f64 {
+    2.0
+}
+
+fn default_boost() -> f64 {
+    1.0
+}
+
+fn default_tags_boost() -> f64 {
+    1.5
 }
 
 impl Config {
     pub fn get_counter(&self) -> i64 {
         self.counter
     }
+
+    pub fn transcriber(&self) -> &str {
+        &self.transcriber
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn play_command(&self) -> &str {
+        &self.play_command
+    }
+
+    pub fn open_command(&self) -> &str {
+        &self.open_command
+    }
+
+    pub fn folder_command(&self) -> &str {
+        &self.folder_command
+    }
+
+    pub fn retention(&self) -> i64 {
+        self.retention
+    }
+
+    /// Index field names with their boost.
+    pub fn boosts(&self) -> [(&'static str, f32); 4] {
+        [
+            ("title", self.title_boost as f32),
+            ("description", self.description_boost as f32),
+            ("transcript", self.transcript_boost as f32),
+            ("tags", self.tags_boost as f32),
+        ]
+    }
 }
 
 impl Default for Config {
@@ -23,6 +118,16 @@ impl Default for Config {
         Self {
             id: "CONFIG".to_string(),
             counter: 1,
+            transcriber: default_transcriber(),
+            model: default_model(),
+            play_command: default_play(),
+            open_command: default_open(),
+            folder_command: default_folder(),
+            retention: default_retention(),
+            title_boost: default_title_boost(),
+            description_boost: default_boost(),
+            transcript_boost: default_boost(),
+            tags_boost: default_tags_boost(),
         }
     }
 }
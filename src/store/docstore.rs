@@ -1,7 +1,12 @@
 //! # DocStore
 
 use anyhow::{anyhow, Result};
-use polodb_core::{bson::Document, ClientCursor, Database};
+use std::sync::{Mutex, MutexGuard};
+
+use polodb_core::{
+    bson::{doc, Document},
+    ClientCursor, Database, TransactionType,
+};
 use serde::de::DeserializeOwned;
 
 use crate::ROOT;
@@ -10,17 +15,24 @@ use super::Entity;
 
 pub struct DocStore {
     pub database: Database,
+    // Held by every write, PoloDB doesn't merge concurrent updates of a document
+    writes: Mutex<()>,
 }
 
 impl DocStore {
     pub fn new() -> Result<Self> {
-        let path = format!("{}/store/CCP.db", ROOT.as_str());
+        Self::open(&format!("{}/store/CCP.db", ROOT.as_str()))
+    }
+
+    pub fn open(path: &str) -> Result<Self> {
         Ok(Self {
             database: Database::open_file(path)?,
+            writes: Mutex::new(()),
         })
     }
 
     pub fn insert<E: Entity>(&self, item: E) -> Result<()> {
+        let _guard = self.lock();
         let col = self.database.collection::<E>(E::collection());
         let _ = col.insert_one(item)?;
 
@@ -51,17 +63,90 @@ impl DocStore {
         Ok(found)
     }
 
+    /// Like `get_all`, leaving the documents undecoded.
+    pub fn get_all_raw<E: Entity>(&self) -> Result<ClientCursor<Document>> {
+        let col = self.database.collection::<Document>(E::collection());
+        let found = col.find(None)?;
+        Ok(found)
+    }
+
+    pub fn get_one_raw<E: Entity>(&self, query: Document) -> Result<Option<Document>> {
+        let col = self.database.collection::<Document>(E::collection());
+        Ok(col.find_one(query)?)
+    }
+
+    /// Increments the field of the matching document and returns it, the
+    /// read can't be interleaved with another increment.
+    pub fn increment<E: Entity + DeserializeOwned>(
+        &self,
+        query: Document,
+        field: &str,
+    ) -> Result<E> {
+        let _guard = self.lock();
+        let col = self.database.collection::<E>(E::collection());
+
+        let mut session = self.database.start_session()?;
+        session.start_transaction(Some(TransactionType::Write))?;
+        let _ = col.update_one_with_session(
+            query.clone(),
+            doc! {"$inc": {field: 1_i64}},
+            &mut session,
+        )?;
+        let found = col.find_one_with_session(query, &mut session)?;
+        session.commit_transaction()?;
+
+        found.ok_or(anyhow!("Unable to find document"))
+    }
+
     pub fn update_one<E: Entity>(&self, query: Document, changes: Document) -> Result<()> {
+        let _guard = self.lock();
         let col = self.database.collection::<E>(E::collection());
         let _ = col.update_one(query, changes)?;
 
         Ok(())
     }
 
+    pub fn delete_one<E: Entity>(&self, query: Document) -> Result<()> {
+        let _guard = self.lock();
+        let col = self.database.collection::<E>(E::collection());
+        let res = col.delete_one(query)?;
+        if res.deleted_count == 0 {
+            return Err(anyhow!("Unable to find document"));
+        }
+
+        Ok(())
+    }
+
     pub fn update_many<E: Entity>(&self, query: Document, changes: Document) -> Result<()> {
+        let _guard = self.lock();
         let col = self.database.collection::<E>(E::collection());
         let _ = col.update_many(query, changes)?;
 
         Ok(())
     }
+
+    /// Copies all documents of `E` from `other`, replacing those with the
+    /// same id. Returns how many were copied.
+    pub fn copy_from<E: Entity>(&self, other: &DocStore) -> Result<usize> {
+        let docs = other
+            .database
+            .collection::<Document>(E::collection())
+            .find(None)?
+            .collect::<Result<Vec<Document>, _>>()?;
+        let _guard = self.lock();
+        let col = self.database.collection::<Document>(E::collection());
+        for doc in &docs {
+            if let Some(id) = doc.get("_id") {
+                let _ = col.delete_one(doc! {"_id": id.clone()})?;
+            }
+            let _ = col.insert_one(doc)?;
+        }
+        Ok(docs.len())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.writes
+            .lock()
+            .expect("Unrecoverable internal system error.")
+    }
 }
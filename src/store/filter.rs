@@ -0,0 +1,297 @@
+//! # Filter
+//!
+//! Filters in the search input, matched exactly instead of by the query parser.
+//!
+//! `tag:cs2 lang:de date:2023-11..2023-12 duration:>30s size:<100MB id:>=10`
+//!
+//! Dates, durations, sizes and ids take a single value, a range `a..b` with
+//! either end left open, or a comparison `>`, `>=`, `<`, `<=`. A leading `-`
+//! excludes the matches. Tags match exactly, `tag:"counter strike"` for
+//! ones with spaces.
+
+use std::ops::Bound;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate};
+use tantivy::{
+    query::{Query, RangeQuery, TermQuery},
+    schema::{IndexRecordOption, Schema},
+    DateTime, Term,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    Tag(String),
+    Language(String),
+    Date(Bound<DateTime>, Bound<DateTime>),
+    // in seconds
+    Duration(Bound<i64>, Bound<i64>),
+    // in bytes
+    Size(Bound<u64>, Bound<u64>),
+    Id(Bound<i64>, Bound<i64>),
+}
+
+/// Separates the filters from the free text, `true` marks excluded filters.
+pub fn split(query: &str) -> Result<(String, Vec<(bool, Filter)>)> {
+    let mut text = Vec::new();
+    let mut filters = Vec::new();
+    for word in words(query) {
+        let (negated, rest) = match word.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, word),
+        };
+        let (key, value) = match rest.split_once(':') {
+            Some(kv) => kv,
+            None => {
+                text.push(word);
+                continue;
+            }
+        };
+        let filter = match key {
+            "tag" | "tags" => Filter::Tag(word_value(key, value.trim_matches('"'))?.to_string()),
+            "lang" | "language" => Filter::Language(word_value(key, value)?.to_lowercase()),
+            "date" => {
+                let (from, to) =
+                    bounds(value, date).ok_or(invalid(key, value, "2023-11..2023-12"))?;
+                Filter::Date(from, to)
+            }
+            "duration" => {
+                let (from, to) = bounds(value, seconds).ok_or(invalid(key, value, ">30s"))?;
+                Filter::Duration(from, to)
+            }
+            "size" => {
+                let (from, to) = bounds(value, bytes).ok_or(invalid(key, value, "<100MB"))?;
+                Filter::Size(from, to)
+            }
+            "id" => {
+                let (from, to) = bounds(value, id).ok_or(invalid(key, value, ">=10"))?;
+                Filter::Id(from, to)
+            }
+            // Left to the query parser
+            _ => {
+                text.push(word);
+                continue;
+            }
+        };
+        filters.push((negated, filter));
+    }
+    Ok((text.join(" "), filters))
+}
+
+impl Filter {
+    pub fn query(&self, schema: &Schema) -> Result<Box<dyn Query>> {
+        let term = |field: &str, value: &str| -> Result<Box<dyn Query>> {
+            let term = Term::from_field_text(schema.get_field(field)?, value);
+            Ok(Box::new(TermQuery::new(term, IndexRecordOption::Basic)))
+        };
+        Ok(match self {
+            Filter::Tag(tag) => term("tags", tag)?,
+            Filter::Language(code) => term("language", code)?,
+            Filter::Date(from, to) => Box::new(RangeQuery::new_date_bounds(
+                String::from("timestamp"),
+                *from,
+                *to,
+            )),
+            Filter::Duration(from, to) => Box::new(RangeQuery::new_i64_bounds(
+                String::from("duration"),
+                *from,
+                *to,
+            )),
+            Filter::Size(from, to) => {
+                Box::new(RangeQuery::new_u64_bounds(String::from("size"), *from, *to))
+            }
+            Filter::Id(from, to) => {
+                Box::new(RangeQuery::new_i64_bounds(String::from("id"), *from, *to))
+            }
+        })
+    }
+}
+
+/// Splits on whitespace outside of double quotes, the quotes are kept.
+pub fn words(query: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    for (i, c) in query.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if let Some(s) = start.take() {
+                    words.push(&query[s..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(s) = start {
+        words.push(&query[s..]);
+    }
+    words
+}
+
+fn word_value<'a>(key: &str, value: &'a str) -> Result<&'a str> {
+    if value.is_empty() {
+        Err(anyhow!("{} filter without a value", key))
+    } else {
+        Ok(value)
+    }
+}
+
+fn invalid(key: &str, value: &str, example: &str) -> anyhow::Error {
+    anyhow!(
+        "invalid {} '{}', expected e.g. {}:{}",
+        key,
+        value,
+        key,
+        example
+    )
+}
+
+// Bounds of a range or comparison, `parse` gives the covered values as start and end (exclusive)
+fn bounds<T: Copy>(value: &str, parse: fn(&str) -> Option<(T, T)>) -> Option<(Bound<T>, Bound<T>)> {
+    use Bound::*;
+    if let Some(v) = value.strip_prefix(">=") {
+        return Some((Included(parse(v)?.0), Unbounded));
+    }
+    if let Some(v) = value.strip_prefix("<=") {
+        return Some((Unbounded, Excluded(parse(v)?.1)));
+    }
+    if let Some(v) = value.strip_prefix('>') {
+        return Some((Included(parse(v)?.1), Unbounded));
+    }
+    if let Some(v) = value.strip_prefix('<') {
+        return Some((Unbounded, Excluded(parse(v)?.0)));
+    }
+    if let Some((from, to)) = value.split_once("..") {
+        let from = match from {
+            "" => Unbounded,
+            f => Included(parse(f)?.0),
+        };
+        let to = match to {
+            "" => Unbounded,
+            t => Excluded(parse(t)?.1),
+        };
+        return Some((from, to));
+    }
+    let (start, end) = parse(value)?;
+    Some((Included(start), Excluded(end)))
+}
+
+// 2023, 2023-11, 2023-11-10 or 10-11-2023, covering the whole year, month or day
+fn date(value: &str) -> Option<(DateTime, DateTime)> {
+    let parts: Vec<&str> = value.split('-').collect();
+    let (start, end) = match parts[..] {
+        [y] if y.len() == 4 => {
+            let start = NaiveDate::from_ymd_opt(y.parse().ok()?, 1, 1)?;
+            (start, start.with_year(start.year() + 1)?)
+        }
+        [y, m] if y.len() == 4 => {
+            let start = NaiveDate::from_ymd_opt(y.parse().ok()?, m.parse().ok()?, 1)?;
+            let end = match start.month() {
+                12 => NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)?,
+                m => start.with_month(m + 1)?,
+            };
+            (start, end)
+        }
+        [_, _, _] => {
+            let start = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .or_else(|_| NaiveDate::parse_from_str(value, "%d-%m-%Y"))
+                .ok()?;
+            (start, start.succ_opt()?)
+        }
+        _ => return None,
+    };
+    // Same as the indexed dates, midnight in UTC
+    let secs = |d: NaiveDate| {
+        let midnight = d.and_hms_opt(0, 0, 0)?.and_utc();
+        Some(DateTime::from_timestamp_secs(midnight.timestamp()))
+    };
+    Some((secs(start)?, secs(end)?))
+}
+
+// 90, 90s, 5m, 1h or combined like 1m30s
+fn seconds(value: &str) -> Option<(i64, i64)> {
+    let mut total = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            's' | 'm' | 'h' => {
+                let unit = match c {
+                    's' => 1,
+                    'm' => 60,
+                    _ => 3600,
+                };
+                total += number.parse::<i64>().ok()? * unit;
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+    if !number.is_empty() {
+        total += number.parse::<i64>().ok()?;
+    } else if value.is_empty() {
+        return None;
+    }
+    Some((total, total + 1))
+}
+
+// 500, 500B, 1.5KB, 100MB or 2GB
+fn bytes(value: &str) -> Option<(u64, u64)> {
+    let upper = value.to_uppercase();
+    let split = upper
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(upper.len());
+    let (number, unit) = upper.split_at(split);
+    let unit = match unit {
+        "" | "B" => 1.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        _ => return None,
+    };
+    let size = (number.parse::<f64>().ok()? * unit) as u64;
+    Some((size, size + 1))
+}
+
+fn id(value: &str) -> Option<(i64, i64)> {
+    let id = value.parse::<i64>().ok()?;
+    Some((id, id + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_split() {
+        let (text, filters) =
+            split("aim tag:CS2 -lang:de duration:>30s size:<1.5MB \"a b\"").unwrap();
+        assert_eq!(text, "aim \"a b\"");
+        assert_eq!(filters[0], (false, Filter::Tag(String::from("CS2"))));
+        assert_eq!(filters[1], (true, Filter::Language(String::from("de"))));
+        assert_eq!(
+            filters[2].1,
+            Filter::Duration(Bound::Included(31), Bound::Unbounded)
+        );
+        assert_eq!(
+            filters[3].1,
+            Filter::Size(Bound::Unbounded, Bound::Excluded(1_500_000))
+        );
+
+        let (_, filters) = split("date:2023-11..2023-12").unwrap();
+        let (from, _) = date("2023-11").unwrap();
+        let (to, _) = date("2024").unwrap();
+        assert_eq!(
+            filters[0].1,
+            Filter::Date(Bound::Included(from), Bound::Excluded(to))
+        );
+        let (_, filters) = split("tag:\"counter strike\"").unwrap();
+        assert_eq!(filters[0].1, Filter::Tag(String::from("counter strike")));
+        assert!(split("duration:>30x").is_err());
+        assert!(split("date:2023-13").is_err());
+        assert_eq!(split("title:aim").unwrap().0, "title:aim");
+    }
+}
@@ -3,10 +3,12 @@
 
 mod config;
 mod docstore;
+mod filter;
 mod searcher;
 
 mod entity;
 pub use entity::Entity;
+pub use filter::words;
 
 use anyhow::Result;
 use config::Config;
@@ -16,6 +18,7 @@ use polodb_core::{
     ClientCursor,
 };
 use searcher::Searcher;
+pub use searcher::{Highlighter, Matcher, Snippet, Sort};
 use serde::de::DeserializeOwned;
 use tantivy::{schema::Schema, Document as FTSDoc};
 
@@ -24,6 +27,9 @@ pub struct Database {
     documents: DocStore,
 }
 
+/// Another database file, only read from.
+pub struct Backup(DocStore);
+
 impl Database {
     pub fn new() -> Result<Self> {
         let searcher = Searcher::new()?;
@@ -47,6 +53,20 @@ impl Database {
         }
     }
 
+    pub fn open_backup(&self, path: &str) -> Result<Backup> {
+        Ok(Backup(DocStore::open(path)?))
+    }
+
+    /// Copies the documents of `E` from the backup, see `copy_config_from`
+    /// for the configuration.
+    pub fn copy_from<E: Entity>(&self, backup: &Backup) -> Result<usize> {
+        self.documents.copy_from::<E>(&backup.0)
+    }
+
+    pub fn copy_config_from(&self, backup: &Backup) -> Result<usize> {
+        self.documents.copy_from::<Config>(&backup.0)
+    }
+
     pub fn get_schema(&self) -> Schema {
         self::Searcher::schema()
     }
@@ -55,15 +75,18 @@ impl Database {
         self.documents.get_one::<Config>(doc! {"_id": "CONFIG"})
     }
 
-    pub fn get_config_incr(&self) -> Result<i64> {
-        let conf = self.documents.get_one::<Config>(doc! {"_id": "CONFIG"})?;
-        let counter = conf.get_counter() + 1;
-        let _ = self.documents.update_one::<Config>(
-            doc! {"_id": "CONFIG"},
-            doc! {"$set": doc! {"counter": counter}},
-        )?;
+    /// Sets the given fields of the configuration.
+    pub fn set_config(&self, changes: Document) -> Result<()> {
+        self.documents
+            .update_one::<Config>(doc! {"_id": "CONFIG"}, doc! {"$set": changes})
+    }
 
-        Ok(counter)
+    /// Allocates the next id, safe to call from several threads.
+    pub fn get_config_incr(&self) -> Result<i64> {
+        let conf = self
+            .documents
+            .increment::<Config>(doc! {"_id": "CONFIG"}, "counter")?;
+        Ok(conf.get_counter())
     }
 
     pub fn insert_indexed<E: Entity>(&self, item: E) -> Result<()> {
@@ -84,17 +107,81 @@ impl Database {
         )
     }
 
-    pub fn search<F: TryFrom<FTSDoc>>(&self, query: &str) -> Result<Vec<F>> {
-        // TODO: Parameterize
-        let res: Vec<F> = self
+    /// Sets the changed fields and replaces the indexed document.
+    pub fn update_indexed<E: Entity>(&self, id: i64, changes: Document, item: &E) -> Result<()> {
+        self.documents
+            .update_one::<E>(doc! {"_id": id}, doc! {"$set": changes})?;
+        self.searcher
+            .replace(id, item.to_document(&self.searcher.schema)?)
+    }
+
+    /// Removes the item from the store and the index.
+    pub fn delete_indexed<E: Entity>(&self, id: i64) -> Result<()> {
+        self.documents.delete_one::<E>(doc! {"_id": id})?;
+        self.searcher.delete(id)
+    }
+
+    /// Indexes the item again, replacing all documents with its id.
+    pub fn index_replace<E: Entity>(&self, id: i64, item: &E) -> Result<()> {
+        self.searcher
+            .replace(id, item.to_document(&self.searcher.schema)?)
+    }
+
+    pub fn index_remove(&self, id: i64) -> Result<()> {
+        self.searcher.delete(id)
+    }
+
+    pub fn indexed_ids(&self) -> Result<Vec<i64>> {
+        self.searcher.ids()
+    }
+
+    pub fn index_empty(&self) -> bool {
+        self.searcher.is_empty()
+    }
+
+    /// Recreates the full text index from the items, `progress` gets the
+    /// number of indexed items and the total.
+    pub fn rebuild<E: Entity>(
+        &self,
+        items: &[E],
+        progress: &mut dyn FnMut(usize, usize),
+    ) -> Result<()> {
+        let docs = items
+            .iter()
+            .map(|x| x.to_document(&self.searcher.schema))
+            .collect::<Result<Vec<FTSDoc>>>()?;
+        let total = docs.len();
+        progress(0, total);
+        self.searcher.rebuild(docs, &mut |i| progress(i, total))
+    }
+
+    pub fn search<F: TryFrom<FTSDoc>>(
+        &self,
+        query: &str,
+        sort: Sort,
+        reverse: bool,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<F>, usize)> {
+        let boosts = self.get_config()?.boosts();
+        let (docs, hits) = self
             .searcher
-            .search(query, 100)?
+            .search(query, &boosts, sort, reverse, offset, limit)?;
+        let res: Vec<F> = docs
             .into_iter()
             .map(|x| TryInto::<F>::try_into(x))
             .filter_map(Result::ok)
             .collect();
 
-        Ok(res)
+        Ok((res, hits))
+    }
+
+    pub fn matcher(&self, query: &str, field: &str) -> Result<Matcher> {
+        self.searcher.matcher(query, field)
+    }
+
+    pub fn highlighter(&self, query: &str, field: &str, chars: usize) -> Result<Highlighter> {
+        self.searcher.highlighter(query, field, chars)
     }
 
     pub fn insert<E: Entity>(&self, item: E) -> Result<()> {
@@ -116,6 +203,14 @@ impl Database {
         self.documents.get_all()
     }
 
+    pub fn get_all_raw<E: Entity>(&self) -> Result<ClientCursor<Document>> {
+        self.documents.get_all_raw::<E>()
+    }
+
+    pub fn get_one_raw<E: Entity>(&self, query: Document) -> Result<Option<Document>> {
+        self.documents.get_one_raw::<E>(query)
+    }
+
     pub fn update_one<E: Entity>(&self, query: Document, changes: Document) -> Result<()> {
         self.documents.update_one::<E>(query, changes)
     }
@@ -3,19 +3,144 @@
 
 use std::{
     fs,
+    ops::Range,
     sync::{Arc, Mutex},
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
 use tantivy::{
     collector::TopDocs,
-    query::QueryParser,
-    schema::{NumericOptions, Schema, TEXT},
-    DateOptions, DateTimePrecision, Document, Index, IndexReader, IndexWriter, ReloadPolicy,
+    directory::MmapDirectory,
+    query::{AllQuery, BooleanQuery, Occur, Query, QueryParser},
+    schema::{Field, NumericOptions, Schema, STRING, TEXT},
+    tokenizer::TextAnalyzer,
+    DateOptions, DateTime, DateTimePrecision, DocAddress, Document, Index, IndexReader,
+    IndexWriter, Order, ReloadPolicy, SnippetGenerator, Term,
 };
 
+use super::filter;
 use crate::ROOT;
 
+// Searched by words without a field name
+const FIELDS: [&str; 4] = ["title", "description", "transcript", "tags"];
+
+// Typos tolerated in the words of text fields
+const FUZZY: u8 = 2;
+
+/// Order of the search results, the fields start with the highest value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sort {
+    #[default]
+    Relevance,
+    Date,
+    Duration,
+    Size,
+    Id,
+}
+
+impl Sort {
+    pub const ALL: [Sort; 5] = [
+        Sort::Relevance,
+        Sort::Date,
+        Sort::Duration,
+        Sort::Size,
+        Sort::Id,
+    ];
+
+    pub fn from_str(input: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|s| s.name() == input.trim().to_lowercase())
+            .ok_or(anyhow!(
+                "unknown sort '{}', expected relevance, date, duration, size or id",
+                input
+            ))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sort::Relevance => "relevance",
+            Sort::Date => "date",
+            Sort::Duration => "duration",
+            Sort::Size => "size",
+            Sort::Id => "id",
+        }
+    }
+
+    /// The following one, wraps around.
+    pub fn next(&self) -> Self {
+        let i = Self::ALL.iter().position(|s| s == self).unwrap_or_default();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+}
+
+/// Best fragment of a text, with the byte ranges of the matched terms.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snippet {
+    pub text: String,
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// Creates the snippets of one field for one query.
+pub struct Highlighter(SnippetGenerator);
+
+impl Highlighter {
+    pub fn snippet(&self, text: &str) -> Snippet {
+        let snippet = self.0.snippet(text);
+        Snippet {
+            text: snippet.fragment().to_string(),
+            highlights: snippet.highlighted().to_vec(),
+        }
+    }
+}
+
+/// Matches texts of one field against the words of a query, with the
+/// typos tolerated by the search.
+pub struct Matcher {
+    tokenizer: TextAnalyzer,
+    terms: Vec<DFA>,
+}
+
+impl Matcher {
+    /// Matches the words of the query searched in `field`, excluded words and
+    /// filters left out.
+    fn new(index: &Index, query: &str, field: &str) -> Result<Self> {
+        let schema = index.schema();
+        let field = schema.get_field(field)?;
+        let fields = FIELDS
+            .into_iter()
+            .map(|f| schema.get_field(f))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+        let (text, _) = filter::split(query)?;
+        let query = QueryParser::for_index(index, fields)
+            .parse_query_lenient(&text)
+            .0;
+        let mut words = Vec::new();
+        included_words(query.as_ref(), field, &mut words);
+        let builder = LevenshteinAutomatonBuilder::new(FUZZY, false);
+        Ok(Self {
+            tokenizer: index.tokenizer_for_field(field)?,
+            terms: words.iter().map(|w| builder.build_dfa(w)).collect(),
+        })
+    }
+
+    pub fn matches(&mut self, text: &str) -> bool {
+        let mut stream = self.tokenizer.token_stream(text);
+        while stream.advance() {
+            let word = &stream.token().text;
+            if self
+                .terms
+                .iter()
+                .any(|t| matches!(t.eval(word), Distance::Exact(_)))
+            {
+                return true;
+            }
+        }
+        false
+    }
+}
+
 pub struct Searcher {
     pub schema: Schema,
     index: Index,
@@ -28,9 +153,19 @@ impl Searcher {
         let schema = Self::schema();
         let path = format!("{}/store/search/", ROOT.as_str());
         let _ = fs::create_dir_all(path.clone());
-        let index = match Index::create_in_dir(path.clone(), schema.to_owned()) {
-            Ok(i) => i,
-            Err(_) => Index::open_in_dir(path).expect("Unable to open Tantivy Database"),
+        let index = if Index::exists(&MmapDirectory::open(&path)?)? {
+            let index = Index::open_in_dir(&path)?;
+            if index.schema() == schema {
+                index
+            } else {
+                // Built with an older schema, reindexed from the documents on startup
+                drop(index);
+                fs::remove_dir_all(&path)?;
+                fs::create_dir_all(&path)?;
+                Index::create_in_dir(&path, schema.to_owned())?
+            }
+        } else {
+            Index::create_in_dir(&path, schema.to_owned())?
         };
         let writer = Arc::new(Mutex::new(index.writer(25_000_000)?));
         let reader = index
@@ -46,6 +181,10 @@ impl Searcher {
         })
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.reader.searcher().num_docs() == 0
+    }
+
     pub fn index_one(&self, doc: Document) -> Result<()> {
         let mut writer = self
             .writer
@@ -55,6 +194,8 @@ impl Searcher {
         let _ = writer.add_document(doc)?;
 
         let _ = writer.commit()?;
+        // Visible to the next search right away
+        self.reader.reload()?;
 
         Ok(())
     }
@@ -70,39 +211,174 @@ impl Searcher {
         }
 
         let _ = writer.commit()?;
+        // Visible to the next search right away
+        self.reader.reload()?;
+
+        Ok(())
+    }
+
+    /// Swaps the document with the given id for the new one.
+    pub fn replace(&self, id: i64, doc: Document) -> Result<()> {
+        let field = self.schema.get_field("id")?;
+        let mut writer = self
+            .writer
+            .lock()
+            .expect("Unrecoverable internal system error.");
+
+        let _ = writer.delete_term(Term::from_field_i64(field, id));
+        let _ = writer.add_document(doc)?;
+
+        let _ = writer.commit()?;
+        self.reader.reload()?;
+
+        Ok(())
+    }
+
+    /// Replaces the whole index with the given documents, the files of the
+    /// old segments are removed afterwards.
+    pub fn rebuild(&self, docs: Vec<Document>, progress: &mut dyn FnMut(usize)) -> Result<()> {
+        let mut writer = self
+            .writer
+            .lock()
+            .expect("Unrecoverable internal system error.");
+
+        let _ = writer.delete_all_documents()?;
+        for (i, doc) in docs.into_iter().enumerate() {
+            let _ = writer.add_document(doc)?;
+            progress(i + 1);
+        }
+
+        let _ = writer.commit()?;
+        self.reader.reload()?;
+        let _ = writer.garbage_collect_files().wait()?;
 
         Ok(())
     }
 
-    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<Document>> {
-        let title = self.schema.get_field("title")?;
-        let text = self.schema.get_field("transcript")?;
-        let desc = self.schema.get_field("description")?;
-        let tags = self.schema.get_field("tags")?;
-        let _ts = self.schema.get_field("timestamp")?;
-        let _id = self.schema.get_field("id")?;
+    pub fn delete(&self, id: i64) -> Result<()> {
+        let field = self.schema.get_field("id")?;
+        let mut writer = self
+            .writer
+            .lock()
+            .expect("Unrecoverable internal system error.");
+
+        let _ = writer.delete_term(Term::from_field_i64(field, id));
+
+        let _ = writer.commit()?;
+        self.reader.reload()?;
+
+        Ok(())
+    }
 
-        // println!("Searching!");
+    /// Ids of all documents, once per document.
+    pub fn ids(&self) -> Result<Vec<i64>> {
+        let searcher = self.reader.searcher();
+        let mut ids = Vec::new();
+        for segment in searcher.segment_readers() {
+            let column = segment.fast_fields().i64("id")?;
+            for doc in segment.doc_ids_alive() {
+                ids.extend(column.first(doc));
+            }
+        }
+        Ok(ids)
+    }
 
+    /// Up to `limit` documents after skipping `offset`, `reverse` starts
+    /// with the lowest value unless sorted by relevance. Words without a
+    /// field name are searched in the fields of `boosts`. Also returns the
+    /// number of hits, unreadable documents are still counted.
+    pub fn search(
+        &self,
+        query: &str,
+        boosts: &[(&str, f32)],
+        sort: Sort,
+        reverse: bool,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<Document>, usize)> {
         let searcher = self.reader.searcher();
 
-        let mut parser = QueryParser::for_index(&self.index, vec![title, text, tags]);
+        let fields = boosts
+            .iter()
+            .map(|(name, _)| self.schema.get_field(name))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+        let mut parser = QueryParser::for_index(&self.index, fields.clone());
         parser.set_conjunction_by_default();
-        parser.set_field_fuzzy(title, false, 2, false);
-        parser.set_field_fuzzy(text, false, 2, false);
-        parser.set_field_fuzzy(desc, false, 2, false);
-        let query = parser.parse_query(query)?;
+        for (field, (name, boost)) in fields.into_iter().zip(boosts) {
+            parser.set_field_boost(field, *boost);
+            // Tags only match exactly
+            if *name != "tags" {
+                parser.set_field_fuzzy(field, false, FUZZY, false);
+            }
+        }
+
+        let (text, filters) = filter::split(query)?;
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        if !text.is_empty() {
+            let parsed = parser
+                .parse_query(&text)
+                .map_err(|e| anyhow!("invalid query: {}", e))?;
+            clauses.push((Occur::Must, parsed));
+        }
+        for (negated, filter) in filters {
+            let occur = if negated { Occur::MustNot } else { Occur::Must };
+            clauses.push((occur, filter.query(&self.schema)?));
+        }
+        // Only excluding filters match nothing on their own
+        if !clauses.iter().any(|(o, _)| *o == Occur::Must) {
+            clauses.push((Occur::Must, Box::new(AllQuery)));
+        }
+        let query = BooleanQuery::new(clauses);
 
-        let docs = searcher.search(&query, &TopDocs::with_limit(limit))?;
+        let top = TopDocs::with_limit(limit).and_offset(offset);
+        let order = if reverse { Order::Asc } else { Order::Desc };
+        let docs = match sort {
+            Sort::Relevance => addresses(searcher.search(&query, &top)?),
+            Sort::Date => addresses(searcher.search(
+                &query,
+                &top.order_by_fast_field::<DateTime>("timestamp", order),
+            )?),
+            Sort::Duration => addresses(
+                searcher.search(&query, &top.order_by_fast_field::<i64>("duration", order))?,
+            ),
+            Sort::Size => {
+                addresses(searcher.search(&query, &top.order_by_fast_field::<u64>("size", order))?)
+            }
+            Sort::Id => {
+                addresses(searcher.search(&query, &top.order_by_fast_field::<i64>("id", order))?)
+            }
+        };
 
-        let mut found = Vec::with_capacity(docs.len());
-        for (_s, addr) in docs {
+        let hits = docs.len();
+        let mut found = Vec::with_capacity(hits);
+        for addr in docs {
             if let Ok(doc) = searcher.doc(addr) {
                 found.push(doc);
             }
         }
 
-        Ok(found)
+        Ok((found, hits))
+    }
+
+    /// Highlights the words of the query, exactly and in `field` only.
+    pub fn highlighter(&self, query: &str, field: &str, chars: usize) -> Result<Highlighter> {
+        let field = self.schema.get_field(field)?;
+        let fields = FIELDS
+            .into_iter()
+            .map(|f| self.schema.get_field(f))
+            .collect::<tantivy::Result<Vec<_>>>()?;
+        // Filters are matched exactly, they have no terms to highlight
+        let (text, _) = filter::split(query)?;
+        let query = QueryParser::for_index(&self.index, fields)
+            .parse_query_lenient(&text)
+            .0;
+        let mut generator = SnippetGenerator::create(&self.reader.searcher(), &query, field)?;
+        generator.set_max_num_chars(chars);
+        Ok(Highlighter(generator))
+    }
+
+    pub fn matcher(&self, query: &str, field: &str) -> Result<Matcher> {
+        Matcher::new(&self.index, query, field)
     }
 
     pub fn schema() -> Schema {
@@ -111,7 +387,10 @@ impl Searcher {
         builder.add_text_field("title", TEXT);
         builder.add_text_field("description", TEXT);
         builder.add_text_field("transcript", TEXT);
-        builder.add_text_field("tags", TEXT);
+        // Not tokenized, a tag only matches as a whole and with the same case
+        builder.add_text_field("tags", STRING);
+        // ISO 639-1 code, only matched exactly
+        builder.add_text_field("language", STRING);
 
         let nums = NumericOptions::default()
             .set_indexed()
@@ -127,9 +406,50 @@ impl Searcher {
 
         let dates = DateOptions::default()
             .set_indexed()
+            .set_fast()
             .set_precision(DateTimePrecision::Seconds);
         builder.add_date_field("timestamp", dates);
 
         builder.build()
     }
 }
+
+// Words of `field` in the query, except those in excluded clauses
+fn included_words(query: &dyn Query, field: Field, words: &mut Vec<String>) {
+    match query.downcast_ref::<BooleanQuery>() {
+        Some(boolean) => {
+            for (occur, clause) in boolean.clauses() {
+                if *occur != Occur::MustNot {
+                    included_words(clause.as_ref(), field, words);
+                }
+            }
+        }
+        None => query.query_terms(&mut |term, _| {
+            if term.field() == field {
+                words.extend(term.value().as_str().map(String::from));
+            }
+        }),
+    }
+}
+
+// Drops the scores or sort values
+fn addresses<T>(docs: Vec<(T, DocAddress)>) -> Vec<DocAddress> {
+    docs.into_iter().map(|(_, addr)| addr).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matcher() {
+        let index = Index::create_in_ram(Searcher::schema());
+        let mut matcher = Matcher::new(&index, "welten -satz tag:x", "transcript").unwrap();
+        assert!(matcher.matches("Hallo Welt"));
+        assert!(!matcher.matches("Zweiter Satz"));
+        let mut matcher =
+            Matcher::new(&index, "title:welt \"zweiter satz\"", "transcript").unwrap();
+        assert!(!matcher.matches("Hallo Welt"));
+        assert!(matcher.matches("Zweiter Satz"));
+    }
+}
@@ -1,6 +1,7 @@
 //! Utility functions
 
-use crate::ROOT;
+use crate::application::{trash, Entry};
+use crate::{DATABASE, ROOT};
 
 use anyhow::Result;
 use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
@@ -23,6 +24,8 @@ pub fn ensure_configured() -> Result<()> {
     let path5 = format!("{}/temp/", ROOT.as_str());
     let path6 = format!("{}/source/", ROOT.as_str());
     let path7 = format!("{}/subs/", ROOT.as_str());
+    let path8 = format!("{}/export/", ROOT.as_str());
+    let path9 = format!("{}/trash/", ROOT.as_str());
 
     let _ = fs::create_dir_all(path1);
     let _ = fs::create_dir_all(path2);
@@ -31,7 +34,32 @@ pub fn ensure_configured() -> Result<()> {
     let _ = fs::create_dir_all(path5);
     let _ = fs::create_dir_all(path6);
     let _ = fs::create_dir_all(path7);
+    let _ = fs::create_dir_all(path8);
+    let _ = fs::create_dir_all(path9);
 
+    // Empty after a schema change
+    if DATABASE.index_empty() {
+        let (entries, broken) = Entry::all()?;
+        DATABASE.rebuild(&entries, &mut |_, _| {})?;
+        if !broken.is_empty() {
+            eprintln!("Skipped undecodable entries {:?} while reindexing", broken);
+        }
+    }
+
+    Ok(())
+}
+
+// Deletes trashed entries older than the configured retention
+pub fn purge_trash() -> Result<()> {
+    // Failures only keep the trash around longer
+    match trash::purge(DATABASE.get_config()?.retention()) {
+        Ok((_, broken)) => {
+            for b in broken {
+                eprintln!("Skipped trash folder {}", b);
+            }
+        }
+        Err(e) => eprintln!("Unable to purge the trash: {:#}", e),
+    }
     Ok(())
 }
 